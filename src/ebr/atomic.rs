@@ -5,10 +5,11 @@ use core::marker::PhantomData;
 use core::mem::{self, MaybeUninit};
 use core::ops::{Deref, DerefMut};
 use core::slice;
-use core::sync::atomic::{AtomicUsize, Ordering};
+use core::sync::atomic::Ordering;
 
 use std::alloc;
 
+use super::primitive::AtomicUsize;
 use super::Guard;
 
 /// Given ordering for the success case in a compare-exchange operation, returns the strongest
@@ -216,6 +217,7 @@ impl<T> Pointable for T {
 // [`alloc::alloc::Layout::extend`] instead.
 #[repr(C)]
 struct Array<T> {
+    /// The number of elements, not bytes
     size: usize,
     elements: [MaybeUninit<T>; 0],
 }
@@ -230,7 +232,7 @@ impl<T> Pointable for [MaybeUninit<T>] {
         let align = mem::align_of::<Array<T>>();
         let layout = alloc::Layout::from_size_align(size, align).unwrap();
         let ptr = alloc::alloc(layout) as *mut Array<T>;
-        (*ptr).size = size;
+        (*ptr).size = len;
         ptr as usize
     }
 
@@ -417,7 +419,7 @@ impl<T: ?Sized + Pointable> Atomic<T> {
     /// This method takes an [`Ordering`] argument which describes the memory ordering of this
     /// operation.
     pub(crate) fn fetch_and<'g>(&self, val: usize, ord: Ordering, _: &'g Guard) -> Shared<'g, T> {
-        unsafe { Shared::from_usize(self.data.fetch_and(val & low_bits::<T>(), ord)) }
+        unsafe { Shared::from_usize(self.data.fetch_and(val | !low_bits::<T>(), ord)) }
     }
 }
 
@@ -568,6 +570,13 @@ impl<T> Owned<T> {
     pub(crate) fn new(init: T) -> Owned<T> {
         Self::init(init)
     }
+
+    /// Converts the owned pointer into a `Box`.
+    pub(crate) fn into_box(self) -> Box<T> {
+        let (raw, _) = decompose_tag::<T>(self.data);
+        mem::forget(self);
+        unsafe { Box::from_raw(raw as *mut _) }
+    }
 }
 
 impl<T: ?Sized + Pointable> Owned<T> {
@@ -4,16 +4,31 @@ use std::sync::Arc;
 
 use super::internal::{Global, Local};
 use super::Guard;
+#[cfg(not(loom))]
 use crate::lazy::Lazy;
 
 /// The global data for the default garbage collector.
+#[cfg(not(loom))]
 static COLLECTOR: Lazy<Collector, fn() -> Collector> = Lazy::new(Collector::new);
 
+#[cfg(not(loom))]
 thread_local! {
     /// The per-thread participant for the default garbage collector.
     static HANDLE: LocalHandle = COLLECTOR.register();
 }
 
+// Loom runs its threads on a single OS thread and starts every execution
+// afresh, so the default collector has to come from loom as well
+#[cfg(loom)]
+loom::lazy_static! {
+    static ref COLLECTOR: Collector = Collector::new();
+}
+
+#[cfg(loom)]
+loom::thread_local! {
+    static HANDLE: LocalHandle = COLLECTOR.register();
+}
+
 /// Pins the current thread.
 #[inline]
 pub(crate) fn pin() -> Guard {
@@ -7,7 +7,9 @@
 //! If an object became garbage in some epoch, then we can be sure that after two advancements no
 //! participant will hold a reference to it. That is the crux of safe memory reclamation.
 
-use core::sync::atomic::{AtomicUsize, Ordering};
+use core::sync::atomic::Ordering;
+
+use super::primitive::AtomicUsize;
 
 /// An epoch that can be marked as pinned or unpinned.
 ///
@@ -75,11 +77,20 @@ pub(super) struct AtomicEpoch {
 
 impl AtomicEpoch {
     /// Creates a new atomic epoch.
+    #[cfg(not(loom))]
     pub(super) const fn new(epoch: Epoch) -> Self {
         let data = AtomicUsize::new(epoch.data);
         AtomicEpoch { data }
     }
 
+    /// Creates a new atomic epoch. Loom's atomics cannot be created in a
+    /// constant.
+    #[cfg(loom)]
+    pub(super) fn new(epoch: Epoch) -> Self {
+        let data = AtomicUsize::new(epoch.data);
+        AtomicEpoch { data }
+    }
+
     /// Loads a value from the atomic epoch.
     #[inline]
     pub(super) fn load(&self, ord: Ordering) -> Epoch {
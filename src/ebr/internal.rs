@@ -49,6 +49,7 @@ use super::collector::{Collector, LocalHandle};
 use super::deferred::Deferred;
 use super::epoch::{AtomicEpoch, Epoch};
 use super::list::{Entry, IsElement, IterError, List};
+use super::primitive::fence;
 use super::queue::{Queue, SIZE_HINT};
 use super::{unprotected, Guard};
 
@@ -257,7 +258,7 @@ impl Global {
     pub(super) fn push_bag(&self, bag: &mut Bag, guard: &Guard) {
         let bag = mem::replace(bag, Bag::new());
 
-        atomic::fence(Ordering::SeqCst);
+        fence(Ordering::SeqCst);
 
         let epoch = self.epoch.load(Ordering::Relaxed);
         self.queue.push(bag.seal(epoch), guard);
@@ -327,7 +328,7 @@ impl Global {
     #[cold]
     fn try_advance(&self, guard: &Guard) -> Epoch {
         let global_epoch = self.epoch.load(Ordering::Relaxed);
-        atomic::fence(Ordering::SeqCst);
+        fence(Ordering::SeqCst);
 
         // TODO(stjepang): `Local`s are stored in a linked list because linked lists are fairly
         // easy to implement in a lock-free manner. However, traversal can be slow due to cache
@@ -351,7 +352,7 @@ impl Global {
                 }
             }
         }
-        atomic::fence(Ordering::Acquire);
+        fence(Ordering::Acquire);
 
         // All pinned participants were pinned in the current global epoch.
         // Now let's advance the global epoch...
@@ -483,7 +484,7 @@ impl Local {
             // Now we must store `new_epoch` into `self.epoch` and execute a `SeqCst` fence.
             // The fence makes sure that any future loads from `Atomic`s will not happen before
             // this store.
-            if cfg!(any(target_arch = "x86", target_arch = "x86_64")) {
+            if cfg!(all(any(target_arch = "x86", target_arch = "x86_64"), not(loom))) {
                 // HACK(stjepang): On x86 architectures there are two different ways of executing
                 // a `SeqCst` fence.
                 //
@@ -508,7 +509,7 @@ impl Local {
                 atomic::compiler_fence(Ordering::SeqCst);
             } else {
                 self.epoch.store(new_epoch, Ordering::Relaxed);
-                atomic::fence(Ordering::SeqCst);
+                fence(Ordering::SeqCst);
             }
 
             // Increment the pin counter.
@@ -6,7 +6,7 @@
 use core::marker::PhantomData;
 use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
 
-use super::{pin, Atomic, Guard, Shared};
+use super::{unprotected, Atomic, Guard, Shared};
 
 /// An entry in a linked list.
 ///
@@ -181,14 +181,16 @@ impl<T, C: IsElement<T>> List<T, C> {
 impl<T, C: IsElement<T>> Drop for List<T, C> {
     fn drop(&mut self) {
         unsafe {
-            let guard = pin();
-            let mut curr = self.head.load(Relaxed, &guard);
+            // Nobody else can access the list, and pinning here would
+            // reenter the collector that owns it
+            let guard = unprotected();
+            let mut curr = self.head.load(Relaxed, guard);
             while let Some(c) = curr.as_ref() {
-                let succ = c.next.load(Relaxed, &guard);
+                let succ = c.next.load(Relaxed, guard);
                 // Verify that all elements have been removed from the list.
                 assert_eq!(succ.tag(), 1);
 
-                C::finalize(curr.deref(), &guard);
+                C::finalize(curr.deref(), guard);
                 curr = succ;
             }
         }
@@ -14,6 +14,7 @@ pub mod deferred;
 pub mod epoch;
 pub mod internal;
 pub mod list;
+pub(crate) mod primitive;
 pub mod queue;
 
 pub(crate) use self::{
@@ -21,6 +22,7 @@ pub(crate) use self::{
     collector::pin,
 };
 
+use atomic::Pointable;
 use deferred::Deferred;
 use internal::Local;
 
@@ -40,7 +42,9 @@ impl Guard {
         }
     }
 
-    pub(super) unsafe fn defer_unchecked<F, R>(&self, f: F)
+    /// Also used by the slab allocator of the mdlist, whose nodes are not
+    /// allocated through `Owned` and so cannot go through `defer_destroy`.
+    pub(crate) unsafe fn defer_unchecked<F, R>(&self, f: F)
     where
         F: FnOnce() -> R,
     {
@@ -51,7 +55,7 @@ impl Guard {
         }
     }
 
-    pub(crate) unsafe fn defer_destroy<T>(&self, ptr: Shared<'_, T>) {
+    pub(crate) unsafe fn defer_destroy<T: ?Sized + Pointable>(&self, ptr: Shared<'_, T>) {
         self.defer_unchecked(move || ptr.into_owned());
     }
 
@@ -0,0 +1,19 @@
+//! The atomic primitives that `Atomic` and the epochs are built on, and the
+//! hint for spinning on them.
+//!
+//! Under `cfg(loom)` these are loom's, so that loom can explore the orderings
+//! of the collector and of everything built on `Atomic`, such as the mdlist.
+//! The remaining atomics, such as the size hint of the garbage queue, stay
+//! plain since no correctness depends on them.
+
+#[cfg(not(loom))]
+pub(crate) use core::sync::atomic::{fence, AtomicUsize};
+#[cfg(loom)]
+pub(crate) use loom::sync::atomic::{fence, AtomicUsize};
+
+/// Spins once while another thread finishes. Under loom this yields, so that
+/// the other thread gets to run instead of the spin going on forever.
+#[cfg(not(loom))]
+pub(crate) use core::hint::spin_loop;
+#[cfg(loom)]
+pub(crate) use loom::thread::yield_now as spin_loop;
@@ -16,7 +16,7 @@ use core::sync::atomic::{
 
 use crate::cachepadded::CachePadded;
 
-use super::{pin, unprotected, Atomic, Guard, Owned, Shared};
+use super::{unprotected, Atomic, Guard, Owned, Shared};
 
 pub(in crate::ebr) static SIZE_HINT: AtomicUsize = AtomicUsize::new(0);
 
@@ -207,12 +207,14 @@ impl<T> Queue<T> {
 impl<T> Drop for Queue<T> {
     fn drop(&mut self) {
         unsafe {
-            let guard = pin();
+            // Nobody else can access the queue, and pinning here would
+            // reenter the collector that owns it
+            let guard = unprotected();
 
-            while self.try_pop(&guard).is_some() {}
+            while self.try_pop(guard).is_some() {}
 
             // Destroy the remaining sentinel node.
-            let sentinel = self.head.load(Relaxed, &guard);
+            let sentinel = self.head.load(Relaxed, guard);
             drop(sentinel.into_owned());
         }
     }
@@ -1,6 +1,7 @@
 use std::{
     io::{Read, Seek, SeekFrom, Write},
     ops::Range,
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicU64, Ordering},
         Arc,
@@ -11,6 +12,7 @@ use rio::Rio;
 use tokio::sync::oneshot;
 
 use crate::{
+    ebr::pin,
     io::{BufReaderWithPos, BufWriterWithPos},
     layout::{PageId, PageSlottedFile, PAGE_SIZE},
     mdlist::MdList,
@@ -26,12 +28,18 @@ pub struct Engine {
     // Size of unallocated memory in the page
     unallocated: Arc<AtomicU64>,
     file: Arc<PageSlottedFile>,
+    path: Arc<PathBuf>,
 }
 
 impl Engine {
     pub fn new(threads: usize) -> Self {
+        Self::open("gen1", threads)
+    }
+
+    /// Stores the values in the file at `path`, which is truncated.
+    pub fn open(path: impl AsRef<Path>, threads: usize) -> Self {
         let ring = rio::new().unwrap();
-        let file = Arc::new(PageSlottedFile::open("gen1", &ring));
+        let file = Arc::new(PageSlottedFile::open(&path, &ring));
 
         Self {
             index: Default::default(),
@@ -45,17 +53,20 @@ impl Engine {
             file,
             cell_ptr: Default::default(),
             unallocated: Arc::new(AtomicU64::new(PAGE_SIZE)),
+            path: Arc::new(path.as_ref().to_path_buf()),
         }
     }
 
     pub async fn get(&self, key: String) -> anyhow::Result<Option<String>> {
         let index = self.index.clone();
+        let path = self.path.clone();
         let (tx, rx) = oneshot::channel();
         self.pool.spawn(move || {
             let res = (|| {
-                if let Some(cmd_pos) = index.get(key.as_str()) {
+                let cmd_pos = index.get(key.as_str(), &pin()).copied();
+                if let Some(cmd_pos) = cmd_pos {
                     let mut reader =
-                        BufReaderWithPos::new(PageSlottedFile::open_additional("gen1")).unwrap();
+                        BufReaderWithPos::new(PageSlottedFile::open_additional(&*path)).unwrap();
                     reader.seek(SeekFrom::Start(cmd_pos.pos))?;
                     let mut cmd_reader = (&mut reader).take(cmd_pos.len);
                     let mut out = String::new();
@@ -76,11 +87,12 @@ impl Engine {
         let index = self.index.clone();
         let cell_ptr = self.cell_ptr.clone();
         let unallocated = self.unallocated.clone();
+        let path = self.path.clone();
         let (tx, rx) = oneshot::channel();
         self.pool.spawn(move || {
             let _ = tx.send((|| {
                 let mut writer =
-                    BufWriterWithPos::new(PageSlottedFile::open_additional("gen1")).unwrap();
+                    BufWriterWithPos::new(PageSlottedFile::open_additional(&*path)).unwrap();
                 let page = cell_ptr.load(Ordering::SeqCst) / PAGE_SIZE;
                 let curr_unallocated = unallocated.load(Ordering::SeqCst);
 
@@ -96,7 +108,7 @@ impl Engine {
                 let _ = writer.write(value.as_bytes());
 
                 cell_ptr.fetch_add(writer.pos - pos, Ordering::SeqCst);
-                index.insert(key.as_ref(), (PageId(page), pos..writer.pos).into());
+                index.insert(key.as_str(), (PageId(page), pos..writer.pos).into());
                 let unallocated_end = unallocated.fetch_sub(content_len, Ordering::SeqCst);
                 let _ = writer.seek(SeekFrom::Current(unallocated_end as i64));
                 // serde_json::to_writer(&mut writer, &key)?;
@@ -150,9 +162,28 @@ impl From<(PageId, Range<u64>)> for CommandPos {
 mod tests {
     use super::*;
 
+    /// A directory of its own for a test, removed along with its files
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("dimensiondb-{}-{}", name, std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
     #[tokio::test]
     async fn test_update() -> anyhow::Result<()> {
-        let engine = Engine::new(1);
+        let dir = TempDir::new("update");
+        let engine = Engine::open(dir.0.join("gen1"), 1);
         engine
             .set(String::from("key0"), String::from("value0"))
             .await?;
@@ -178,7 +209,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_allocate_page() -> anyhow::Result<()> {
-        let engine = Engine::new(1);
+        let dir = TempDir::new("allocate_page");
+        let engine = Engine::open(dir.0.join("gen1"), 1);
         for k in 0..4 {
             engine.set(format!("key{k}"), format!("value{k}")).await?;
         }
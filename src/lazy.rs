@@ -7,6 +7,8 @@ use std::sync::atomic::{
     Ordering::{Acquire, SeqCst},
 };
 
+use crate::ebr::primitive::spin_loop;
+
 /// A lazily initialized value
 pub struct Lazy<T, F> {
     value: AtomicPtr<T>,
@@ -64,9 +66,7 @@ where
             .compare_exchange(false, true, SeqCst, SeqCst)
             .is_err()
         {
-            // `hint::spin_loop` requires Rust 1.49.
-            #[allow(deprecated)]
-            std::sync::atomic::spin_loop_hint();
+            spin_loop();
         }
 
         {
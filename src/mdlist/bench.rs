@@ -0,0 +1,339 @@
+//! Benchmarks for the mdlist, ignored by default. Run one with
+//!
+//! ```text
+//! cargo test --release <name> -- --ignored --nocapture
+//! ```
+//!
+//! - `bench_radix` compares integer key encodings across `DIM`, see
+//!   [`super::coords`]. Fewer, wider digits make nodes with more children in
+//!   each dimension, while more, narrower digits make `locate_pred` walk
+//!   through more dimensions.
+//! - `bench_hashed` compares string keys placed by their bytes in an
+//!   [`MdList`] with the same keys placed by their hash in a
+//!   [`HashedMdList`].
+//! - `bench_bulk_load` builds a map from sorted keys, one insert at a time
+//!   and with [`MdList::from_sorted_iter`].
+//! - `bench_alloc` times inserts, replacements and removes, which allocate or
+//!   retire a node each. Run it with and without `--features slab` to
+//!   compare the slab allocator with the global one.
+//! - `bench_memory` counts the bytes per key of the nodes, with the child
+//!   pointers stored sparsely and as a full array per node, before and after
+//!   removing half of the keys.
+
+use core::sync::atomic::Ordering::Acquire;
+use std::time::{Duration, Instant};
+
+use rayon::prelude::*;
+
+use super::children::Children;
+use super::{HashedMdList, IsElement, List, MdList, Node, NodeWithValue, ToCoords};
+use crate::ebr::{pin, Atomic, Guard, Shared};
+
+const KEYS: u64 = 200_000;
+
+fn keys(spread: u32) -> Vec<u64> {
+    (0..KEYS)
+        .map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> (64 - spread))
+        .collect()
+}
+
+/// Nodes that `locate_pred` steps through to find `coords`.
+unsafe fn hops<const DIM: usize, T, C: IsElement<DIM, T>>(
+    list: &List<DIM, T, C>,
+    coords: [u8; DIM],
+    guard: &Guard,
+) -> usize {
+    let mut hops = 0;
+    let mut dc = 0;
+    let head = list.head.load(Acquire, guard);
+    let mut curr = head.deref().children.load(0, Acquire, guard);
+    while dc < DIM {
+        while !curr.is_null() && coords[dc] > curr.deref().coords[dc] {
+            hops += 1;
+            curr = curr.deref().children.load(dc, Acquire, guard);
+        }
+        if curr.is_null() || coords[dc] < curr.deref().coords[dc] {
+            break;
+        }
+        dc += 1;
+    }
+    hops + 1
+}
+
+/// Average number of children over the nodes that have any.
+unsafe fn fan_out<const DIM: usize, T, C: IsElement<DIM, T>>(
+    list: &List<DIM, T, C>,
+    guard: &Guard,
+) -> f64 {
+    let (mut parents, mut children) = (0, 0);
+    let head = list.head.load(Acquire, guard);
+    let mut stack: Vec<(Shared<'_, Node<DIM>>, usize)> = vec![(head, 0)];
+    while let Some((node, dim)) = stack.pop() {
+        // Children before `dim` were adopted by the node that replaced it
+        let count = stack.len();
+        for d in dim..DIM {
+            let child = node.deref().children.load(d, Acquire, guard);
+            if !child.is_null() {
+                stack.push((child, d));
+            }
+        }
+        if stack.len() > count {
+            parents += 1;
+            children += stack.len() - count;
+        }
+    }
+    children as f64 / parents as f64
+}
+
+fn bench<const DIM: usize>(spread: u32)
+where
+    u64: ToCoords<DIM>,
+{
+    let keys = keys(spread);
+    let map = MdList::<u64, u64, DIM>::new();
+
+    let start = Instant::now();
+    keys.par_iter().for_each(|&key| map.insert(key, key));
+    let insert = start.elapsed();
+
+    let guard = &pin();
+    let start = Instant::now();
+    for &key in &keys {
+        assert_eq!(map.get(key, guard), Some(&key));
+    }
+    let get = start.elapsed();
+
+    let (hops, fan_out) = unsafe {
+        let hops: usize = keys
+            .iter()
+            .map(|&key| hops(&map.list, key.to_coords(), guard))
+            .sum();
+        (hops as f64 / keys.len() as f64, fan_out(&map.list, guard))
+    };
+
+    let per_key = |d: Duration| d.as_nanos() as f64 / keys.len() as f64;
+    println!(
+        "{:>3} {:>6} {:>10.1} {:>10.1} {:>8.1} {:>8.2}",
+        DIM,
+        spread,
+        per_key(insert),
+        per_key(get),
+        hops,
+        fan_out
+    );
+}
+
+#[test]
+#[ignore]
+fn bench_radix() {
+    println!(
+        "{:>3} {:>6} {:>10} {:>10} {:>8} {:>8}",
+        "dim", "bits", "insert ns", "get ns", "hops", "fan-out"
+    );
+    // Dense keys share their leading digits, sparse ones do not
+    for spread in [20, 64] {
+        bench::<8>(spread);
+        bench::<16>(spread);
+        bench::<32>(spread);
+        bench::<64>(spread);
+    }
+}
+
+/// Prefixed string keys, placed by their bytes and by their hash.
+#[test]
+#[ignore]
+fn bench_hashed() {
+    let keys = (0..KEYS)
+        .map(|i| format!("user#{:08}", i.wrapping_mul(0x9e37_79b9) % KEYS))
+        .collect::<Vec<_>>();
+    let guard = &pin();
+
+    let ordered = MdList::<String, u64>::new();
+    let start = Instant::now();
+    keys.par_iter()
+        .for_each(|key| ordered.insert(key.as_str(), 0));
+    let insert = start.elapsed();
+    let start = Instant::now();
+    keys.iter()
+        .for_each(|key| assert!(ordered.get(key.as_str(), guard).is_some()));
+    let get = start.elapsed();
+    let depth = keys
+        .iter()
+        .map(|key| unsafe { hops(&ordered.list, key.as_str().to_coords(), guard) })
+        .sum::<usize>();
+    println!(
+        "ordered: insert {:?} get {:?} hops {:.1}",
+        insert,
+        get,
+        depth as f64 / keys.len() as f64
+    );
+
+    let hashed = HashedMdList::<String, u64>::new();
+    let start = Instant::now();
+    keys.par_iter()
+        .for_each(|key| hashed.insert(key.clone(), 0));
+    let insert = start.elapsed();
+    let start = Instant::now();
+    keys.iter()
+        .for_each(|key| assert!(hashed.get(key.as_str(), guard).is_some()));
+    let get = start.elapsed();
+    let depth = keys
+        .iter()
+        .map(|key| unsafe { hops(&hashed.list, hashed.coords(key.as_str()), guard) })
+        .sum::<usize>();
+    println!(
+        "hashed:  insert {:?} get {:?} hops {:.1}",
+        insert,
+        get,
+        depth as f64 / keys.len() as f64
+    );
+}
+
+/// Building a map from sorted keys, one insert at a time and in bulk.
+#[test]
+#[ignore]
+fn bench_bulk_load() {
+    let mut keys = keys(64);
+    keys.sort_unstable();
+    keys.dedup();
+
+    let start = Instant::now();
+    let map = MdList::<u64, u64>::new();
+    keys.iter().for_each(|&key| map.insert(key, key));
+    println!("insert:    {:?}", start.elapsed());
+
+    let start = Instant::now();
+    let map = MdList::<u64, u64>::from_sorted_iter(keys.iter().map(|&key| (key, key)));
+    println!("bulk load: {:?}", start.elapsed());
+    assert_eq!(map.iter(&pin()).count(), keys.len());
+}
+
+/// Inserts, replacements and removes, which allocate or retire a node each.
+/// Compare the allocators by running it with and without `--features slab`.
+#[test]
+#[ignore]
+fn bench_alloc() {
+    let keys = keys(64);
+    let map = MdList::<u64, u64>::new();
+
+    let start = Instant::now();
+    keys.par_iter().for_each(|&key| map.insert(key, key));
+    let insert = start.elapsed();
+    let start = Instant::now();
+    keys.par_iter().for_each(|&key| map.insert(key, key + 1));
+    let replace = start.elapsed();
+    let start = Instant::now();
+    keys.par_iter().for_each(|&key| {
+        map.remove(key, &pin());
+    });
+    let remove = start.elapsed();
+
+    let allocator = if cfg!(feature = "slab") {
+        "slab"
+    } else {
+        "global"
+    };
+    println!(
+        "{}: insert {:?} replace {:?} remove {:?}",
+        allocator, insert, replace, remove
+    );
+}
+
+/// Nodes in the list with an element, nodes without one (the head and
+/// placeholders), how many nodes have allocated the child pointers past
+/// their inline ones, and how many of those pointers there are.
+unsafe fn nodes<const DIM: usize, T, C: IsElement<DIM, T>>(
+    list: &List<DIM, T, C>,
+    guard: &Guard,
+) -> (usize, usize, usize, usize) {
+    let (mut nodes, mut bare, mut spilled, mut pointers) = (0, 0, 0, 0);
+    let head = list.head.load(Acquire, guard);
+    let mut stack: Vec<(Shared<'_, Node<DIM>>, usize)> = vec![(head, 0)];
+    while let Some((node, dim)) = stack.pop() {
+        if node == head || node.deref().is_placeholder(guard) {
+            bare += 1;
+        } else {
+            nodes += 1;
+        }
+        let rest = node.deref().children.spilled(guard);
+        spilled += (rest > 0) as usize;
+        pointers += rest;
+        for d in dim..DIM {
+            let child = node.deref().children.load(d, Acquire, guard);
+            if !child.is_null() {
+                stack.push((child, d));
+            }
+        }
+    }
+    (nodes, bare, spilled, pointers)
+}
+
+fn memory<const DIM: usize>(spread: u32)
+where
+    u64: ToCoords<DIM>,
+{
+    let node = core::mem::size_of::<NodeWithValue<DIM, u64>>();
+    let bare_node = core::mem::size_of::<Node<DIM>>();
+    let pointer = core::mem::size_of::<Atomic<Node<DIM>>>();
+    // The same nodes with a pointer for every dimension
+    let children = core::mem::size_of::<Children<DIM>>();
+    let (full, bare_full) = (
+        node - children + DIM * pointer,
+        bare_node - children + DIM * pointer,
+    );
+
+    let keys = keys(spread);
+    let map = MdList::<u64, u64, DIM>::new();
+    keys.par_iter().for_each(|&key| map.insert(key, key));
+    let guard = &pin();
+    let report = |keys: usize| {
+        let (nodes, bare, spilled, pointers) = unsafe { nodes(&map.list, guard) };
+        // Spilled pointers are preceded by their count
+        let bytes = nodes * node
+            + bare * bare_node
+            + spilled * core::mem::size_of::<usize>()
+            + pointers * pointer;
+        println!(
+            "{:>3} {:>6} {:>8} {:>8} {:>9.1} {:>9.1}",
+            DIM,
+            spread,
+            keys,
+            spilled,
+            (nodes * full + bare * bare_full) as f64 / keys as f64,
+            bytes as f64 / keys as f64,
+        );
+    };
+    report(map.iter(guard).count());
+
+    // Removed nodes that had children leave placeholders behind
+    keys.par_iter().step_by(2).for_each(|&key| {
+        map.remove(key, &pin());
+    });
+    report(map.iter(guard).count());
+}
+
+/// Bytes per key of the nodes, with `u64` values, and the same for a full
+/// array of child pointers per node. The second row of each is after
+/// removing half of the keys. With 20 random bits per key:
+///
+/// ```text
+/// dim    full   sparse   sparse after removing half
+///   8    88.0     72.0     73.3 (full 89.6)
+///  16   160.0     80.0    103.6 (full 209.8)
+///  32   304.0     96.7    126.2 (full 403.0)
+///  64   592.0    135.9    197.9 (full 880.5)
+/// ```
+#[test]
+#[ignore]
+fn bench_memory() {
+    println!(
+        "{:>3} {:>6} {:>8} {:>8} {:>9} {:>9}",
+        "dim", "bits", "keys", "spilled", "full B", "sparse B"
+    );
+    for spread in [20, 64] {
+        memory::<8>(spread);
+        memory::<16>(spread);
+        memory::<32>(spread);
+        memory::<64>(spread);
+    }
+}
@@ -0,0 +1,201 @@
+//! Building an [`MdList`] from keys that are already sorted, see
+//! [`MdList::from_sorted_iter`].
+
+use core::iter::Peekable;
+use core::sync::atomic::Ordering::Relaxed;
+
+use super::{IsElement, List, MdList, Node, NodeWithValue, ToCoords};
+use crate::ebr::{unprotected, Shared};
+
+/// The first chunk of a run of longer keys, along with the rest of them
+type Run<const DIM: usize, T> = ([u8; DIM], Vec<(Vec<[u8; DIM]>, T)>);
+
+/// Appends nodes in ascending order of their coordinates to a list that is
+/// not shared yet.
+///
+/// A node that is larger than everything in the list hangs off the path to
+/// the last node. If it first differs from the last node in dimension `d`,
+/// `List::locate_pred` would follow that path up to the first node that the
+/// path leaves through a dimension past `d`, or to its end. Every node below
+/// that one in dimension `d` would be larger than the last node, so there are
+/// none and the new node becomes its child in `d`, without any children to
+/// adopt.
+struct Appender<'a, const DIM: usize, T, C: IsElement<DIM, T>> {
+    list: &'a List<DIM, T, C>,
+    // The path from the root to the last node, along with the dimension we
+    // reached each node through
+    path: Vec<(Shared<'a, Node<DIM>>, usize)>,
+}
+
+impl<'a, const DIM: usize, T, C: IsElement<DIM, T>> Appender<'a, DIM, T, C> {
+    fn new(list: &'a List<DIM, T, C>) -> Self {
+        Self { list, path: vec![] }
+    }
+
+    fn push(&mut self, container: T) {
+        unsafe {
+            let guard = unprotected();
+            let container = C::alloc(container, guard);
+            let entry = C::entry_of(container.deref());
+            let node = Shared::from(entry as *const Node<DIM>);
+
+            let Some(&(last, _)) = self.path.last() else {
+                let head = self.list.head.load(Relaxed, guard);
+                head.deref().children.slot(0, guard).store(node, Relaxed);
+                self.path.push((node, 0));
+                return;
+            };
+
+            let (prev, coords) = (&last.deref().coords, &entry.coords);
+            let dim = (0..DIM)
+                .find(|&d| prev[d] != coords[d])
+                .expect("coordinates are unique");
+            assert!(
+                coords[dim] > prev[dim],
+                "nodes are appended in ascending order"
+            );
+
+            while self.path.last().is_some_and(|&(_, via)| via > dim) {
+                self.path.pop();
+            }
+            let (pred, _) = *self.path.last().unwrap();
+
+            // Same as an insert at this position, see `List::insert_at`
+            entry.children.reset(dim, guard);
+            pred.deref().children.slot(dim, guard).store(node, Relaxed);
+            self.path.push((node, dim));
+        }
+    }
+}
+
+impl<const DIM: usize, K, T> MdList<K, T, DIM> {
+    /// Builds a map from entries sorted by key.
+    ///
+    /// The nodes are linked up directly instead of being inserted one by
+    /// one, which takes neither a lookup nor a CAS per entry. Of entries with
+    /// the same key, the last one wins.
+    ///
+    /// Panics if the keys are not sorted.
+    pub fn from_sorted_iter<Q, I>(entries: I) -> Self
+    where
+        Q: ToCoords<DIM>,
+        I: IntoIterator<Item = (Q, T)>,
+    {
+        Self::from_sorted_chunks(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.to_chunks(), value))
+                .peekable(),
+        )
+    }
+
+    fn from_sorted_chunks<I>(mut entries: Peekable<I>) -> Self
+    where
+        I: Iterator<Item = (Vec<[u8; DIM]>, T)>,
+    {
+        let map = Self::new();
+        let mut keys = Appender::new(&map.list);
+        let mut longer = Appender::new(&map.overflow);
+
+        // Longer keys are sorted by their first chunk, so the ones that end
+        // up in the same nested map come in a row
+        let mut nested: Option<Run<DIM, T>> = None;
+        let mut flush = |nested: Option<Run<DIM, T>>| {
+            if let Some((chunk, rest)) = nested {
+                let level = Self::from_sorted_chunks(rest.into_iter().peekable());
+                longer.push(NodeWithValue::new(chunk, level));
+            }
+        };
+
+        while let Some((mut chunks, value)) = entries.next() {
+            match entries.peek() {
+                Some((next, _)) if *next == chunks => continue,
+                Some((next, _)) => assert!(*next > chunks, "keys are not sorted"),
+                None => {}
+            }
+
+            if chunks.len() == 1 {
+                keys.push(NodeWithValue::new(chunks[0], value));
+                continue;
+            }
+
+            let chunk = chunks.remove(0);
+            match &mut nested {
+                Some((first, rest)) if *first == chunk => rest.push((chunks, value)),
+                _ => flush(nested.replace((chunk, vec![(chunks, value)]))),
+            }
+        }
+        flush(nested);
+
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use super::*;
+    use crate::ebr::pin;
+
+    #[test]
+    fn test_from_sorted_iter() {
+        let guard = &pin();
+        let keys = (0..5_000_u64)
+            .map(|i| i.wrapping_mul(2_654_435_761) % 100_000)
+            .collect::<std::collections::BTreeSet<_>>();
+
+        let l = MdList::<u64, u64>::from_sorted_iter(keys.iter().map(|&k| (k, k)));
+        let found = l.iter(guard).map(|(k, &v)| (k, v)).collect::<Vec<_>>();
+        assert_eq!(found, keys.iter().map(|&k| (k, k)).collect::<Vec<_>>());
+        for &k in &keys {
+            assert_eq!(l.get(k, guard), Some(&k));
+        }
+
+        // The list takes concurrent updates as usual
+        keys.par_iter().for_each(|&k| {
+            let guard = &pin();
+            if k % 2 == 0 {
+                assert_eq!(l.remove(k, guard), Some(&k));
+            } else {
+                l.insert(k + 1, 0);
+            }
+        });
+        for &k in &keys {
+            let expected = if k % 2 == 0 { None } else { Some(&k) };
+            assert_eq!(l.get(k, guard), expected);
+        }
+        assert!(l.validate(guard).is_ok(), "{}", l.validate(guard));
+    }
+
+    #[test]
+    fn test_from_sorted_long_keys() {
+        let guard = &pin();
+        let mut keys = ["", "a", "abcd", "abcde", "abcdef", "abcdefghi", "abce", "b"]
+            .iter()
+            .map(|k| k.to_string())
+            .collect::<Vec<_>>();
+        keys.sort();
+
+        // Later duplicates win
+        let entries = keys
+            .iter()
+            .enumerate()
+            .flat_map(|(i, k)| [(k.as_str(), 0), (k.as_str(), i)]);
+        let l = MdList::<String, usize, 4>::from_sorted_iter(entries);
+
+        let found = l.iter(guard).map(|(k, &v)| (k, v)).collect::<Vec<_>>();
+        let expected = keys.iter().cloned().zip(0..).collect::<Vec<_>>();
+        assert_eq!(found, expected);
+
+        l.insert("abcdeg", 10);
+        assert_eq!(l.get("abcdeg", guard), Some(&10));
+        assert_eq!(l.get("abcdefghi", guard), Some(&5));
+    }
+
+    #[test]
+    #[should_panic(expected = "keys are not sorted")]
+    fn test_from_unsorted_iter() {
+        MdList::<u64, u64>::from_sorted_iter([(1_u64, 1), (3, 3), (2, 2)]);
+    }
+}
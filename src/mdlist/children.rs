@@ -0,0 +1,175 @@
+//! The child pointers of a [`Node`], stored sparsely.
+//!
+//! A node reached through dimension `d` only has children from `d` on, and
+//! those mostly within a few dimensions past `d`: keys that share a prefix
+//! with the node get rarer with every coordinate. So a node keeps `INLINE`
+//! pointers, starting at the dimension it was first linked through, which we
+//! call its base. The pointers past those are allocated together by the
+//! first write to any of them, as many as there are dimensions left. Until
+//! then they read as null, which is what they would hold, and installing
+//! them is a CAS of its own, so inserts stay lock-free.
+//!
+//! Pointers before the base belong to whichever node the node was inserted
+//! or adopted under, and read as null marked with `ADP`. A node that gets
+//! adopted further down keeps its base, the pointers between its base and
+//! the dimension it is now reached through are marked by the adoption as
+//! before. Unlinks never move a node to a dimension before its base, see
+//! [`List::unlink`](super::List::unlink).
+
+use core::mem::MaybeUninit;
+use core::sync::atomic::Ordering::{self, AcqRel, Acquire, Relaxed};
+
+use super::{List, Node};
+use crate::ebr::primitive::AtomicUsize;
+use crate::ebr::{unprotected, Atomic, Guard, Owned, Shared};
+
+/// Pointers stored in the node itself
+const INLINE: usize = 4;
+
+#[derive(Debug)]
+pub struct Children<const DIM: usize> {
+    /// The dimension the node was first linked through. Only changes before
+    /// the node is shared.
+    base: AtomicUsize,
+    inline: [Atomic<Node<DIM>>; INLINE],
+    /// The pointers from `base + INLINE` on. Null marked with `FRZ` if the
+    /// node was frozen before any of them were allocated.
+    rest: Atomic<[MaybeUninit<Atomic<Node<DIM>>>]>,
+}
+
+impl<const DIM: usize> Children<DIM> {
+    const ADP: usize = List::<DIM, Node<DIM>>::ADP;
+    const FRZ: usize = List::<DIM, Node<DIM>>::FRZ;
+
+    pub(super) fn new() -> Self {
+        Self {
+            base: AtomicUsize::new(0),
+            inline: [(); INLINE].map(|_| Atomic::null()),
+            rest: Atomic::null(),
+        }
+    }
+
+    /// Clears the pointers of a node that is about to be linked through
+    /// dimension `base`. The node must not be shared yet.
+    pub(super) fn reset(&self, base: usize, guard: &Guard) {
+        for child in &self.inline {
+            child.store(Shared::null(), Relaxed);
+        }
+        if let Some(rest) = self.rest(guard) {
+            // Sized for the old base
+            if base != self.base.load(Relaxed) {
+                unsafe { drop(self.rest.swap(Shared::null(), Relaxed, guard).into_owned()) };
+            } else {
+                for child in rest {
+                    child.store(Shared::null(), Relaxed);
+                }
+            }
+        }
+        self.base.store(base, Relaxed);
+    }
+
+    fn rest<'g>(&self, guard: &'g Guard) -> Option<&'g [Atomic<Node<DIM>>]> {
+        let rest = unsafe { self.rest.load(Acquire, guard).as_ref()? };
+        Some(Self::init(rest))
+    }
+
+    fn init(rest: &[MaybeUninit<Atomic<Node<DIM>>>]) -> &[Atomic<Node<DIM>>] {
+        // Every pointer is initialized when the array is allocated
+        unsafe { &*(rest as *const [MaybeUninit<_>] as *const [Atomic<Node<DIM>>]) }
+    }
+
+    /// How many pointers there are past the inline ones.
+    fn past(base: usize) -> usize {
+        DIM.saturating_sub(base + INLINE)
+    }
+
+    /// Loads the child in dimension `d`.
+    #[inline]
+    pub(super) fn load<'g>(
+        &self,
+        d: usize,
+        ord: Ordering,
+        guard: &'g Guard,
+    ) -> Shared<'g, Node<DIM>> {
+        let base = self.base.load(Relaxed);
+        if d < base {
+            return Shared::null().with_tag(Self::ADP);
+        }
+        if d < base + INLINE {
+            return self.inline[d - base].load(ord, guard);
+        }
+        let rest = self.rest.load(Acquire, guard);
+        match unsafe { rest.as_ref() } {
+            Some(r) => Self::init(r)[d - base - INLINE].load(ord, guard),
+            None => Shared::null().with_tag(rest.tag()),
+        }
+    }
+
+    /// Returns the pointer to the child in dimension `d`, to change it. The
+    /// pointers past the inline ones are allocated if they are not yet.
+    pub(super) fn slot<'g>(&'g self, d: usize, guard: &'g Guard) -> &'g Atomic<Node<DIM>> {
+        let base = self.base.load(Relaxed);
+        debug_assert!(d >= base, "dimension {} is before the base {}", d, base);
+        if d < base + INLINE {
+            return &self.inline[d - base];
+        }
+
+        loop {
+            let rest = self.rest.load(Acquire, guard);
+            if let Some(r) = unsafe { rest.as_ref() } {
+                return &Self::init(r)[d - base - INLINE];
+            }
+
+            // Pointers allocated after the node was frozen are frozen too
+            let mut new = Owned::<[MaybeUninit<_>]>::init(Self::past(base));
+            for child in new.iter_mut() {
+                child.write(Atomic::from(Shared::null().with_tag(rest.tag())));
+            }
+            // If another thread got there first, ours is dropped
+            let _ = self.rest.compare_and_set(rest, new, AcqRel, guard);
+        }
+    }
+
+    /// Marks the pointers from dimension `from` on with `FRZ`, so that no
+    /// CAS on them succeeds anymore. If the pointers past the inline ones
+    /// are not allocated, they are frozen as a whole instead.
+    pub(super) fn freeze(&self, from: usize, guard: &Guard) {
+        let base = self.base.load(Relaxed);
+        for d in from.max(base)..DIM.min(base + INLINE) {
+            self.inline[d - base].fetch_or(Self::FRZ, AcqRel, guard);
+        }
+
+        let rest = match self.rest.compare_and_set(
+            Shared::null(),
+            Shared::null().with_tag(Self::FRZ),
+            AcqRel,
+            guard,
+        ) {
+            Ok(_) => return,
+            Err(e) => match unsafe { e.current.as_ref() } {
+                Some(rest) => Self::init(rest),
+                // Frozen as a whole by another helper of the unlink
+                None => return,
+            },
+        };
+        for d in from.max(base + INLINE)..DIM {
+            rest[d - base - INLINE].fetch_or(Self::FRZ, AcqRel, guard);
+        }
+    }
+
+    /// How many pointers outside the node have been allocated.
+    pub(super) fn spilled(&self, guard: &Guard) -> usize {
+        self.rest(guard).map_or(0, <[_]>::len)
+    }
+}
+
+impl<const DIM: usize> Drop for Children<DIM> {
+    fn drop(&mut self) {
+        unsafe {
+            let rest = self.rest.load(Relaxed, unprotected());
+            if !rest.is_null() {
+                drop(rest.into_owned());
+            }
+        }
+    }
+}
@@ -0,0 +1,316 @@
+//! Composite keys, made of several fields packed into consecutive dimensions.
+//!
+//! Every field is encoded on its own and the encodings are concatenated,
+//! most significant field first. Numbers take the base 16 digits of their
+//! scalar encoding, see [`super::coords`], without the padding: two digits
+//! per byte of the type. Strings take one dimension per byte, with zero bytes
+//! escaped as `0x00 0xff`, followed by a `0x00 0x00` terminator. The escape
+//! sorts above the terminator, so a string still sorts before the strings it
+//! is a prefix of, whatever the fields after it.
+//!
+//! Each field encoding is order-preserving, and none is a prefix of another
+//! one of the same type, so comparing the concatenations compares the fields
+//! in order. It also means the zero padding at the end never makes two keys
+//! collide. A composite key that does not fit in `DIM` dimensions is split
+//! into chunks like a byte string, see [`ToCoords::to_chunks`].
+//!
+//! The encoding of the leading fields is a prefix of the encoding of the
+//! whole key, so [`super::MdList::starts_with`] with a [`CoordsBuilder`] of
+//! the leading fields finds all keys that start with them.
+
+use super::coords::{chunked, escaped, padded, unescaped};
+use super::{FromCoords, ToCoords};
+
+/// A field of a composite key.
+pub trait ToField {
+    /// Appends the coordinates of the field to `coords`.
+    fn write_coords(&self, coords: &mut Vec<u8>);
+}
+
+/// The inverse of [`ToField`].
+pub trait FromField: Sized {
+    /// Reads the field from the front of `coords`, advancing it past the
+    /// field. Missing coordinates are read as padding.
+    fn read_coords(coords: &mut &[u8]) -> Self;
+}
+
+/// Packs fields into the coordinates of a composite key, for keys that are
+/// not tuples.
+///
+/// ```ignore
+/// impl ToCoords<16> for Event {
+///     fn to_coords(self) -> [u8; 16] {
+///         self.to_builder().to_coords()
+///     }
+///
+///     fn to_chunks(self) -> Vec<[u8; 16]> {
+///         self.to_builder().to_chunks()
+///     }
+/// }
+///
+/// impl Event {
+///     fn to_builder(&self) -> CoordsBuilder {
+///         CoordsBuilder::new()
+///             .field(self.tenant)
+///             .field(&self.entity)
+///             .field(self.timestamp)
+///     }
+/// }
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CoordsBuilder {
+    coords: Vec<u8>,
+}
+
+impl CoordsBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `field` to the key.
+    #[inline]
+    pub fn field<F: ToField>(mut self, field: F) -> Self {
+        field.write_coords(&mut self.coords);
+        self
+    }
+}
+
+impl AsRef<[u8]> for CoordsBuilder {
+    fn as_ref(&self) -> &[u8] {
+        &self.coords
+    }
+}
+
+impl<const DIM: usize> ToCoords<DIM> for CoordsBuilder {
+    // The fields are encoded already, and contain zeros
+    #[inline]
+    fn to_coords(self) -> [u8; DIM] {
+        padded(&self.coords)
+    }
+
+    #[inline]
+    fn to_chunks(self) -> Vec<[u8; DIM]> {
+        chunked(&self.coords)
+    }
+}
+
+/// Reads the fields of a composite key back, in the order they were packed.
+pub struct CoordsReader<'a> {
+    coords: &'a [u8],
+}
+
+impl<'a> CoordsReader<'a> {
+    pub fn new(coords: &'a [u8]) -> Self {
+        Self { coords }
+    }
+
+    /// Reads the next field.
+    #[inline]
+    pub fn field<F: FromField>(&mut self) -> F {
+        F::read_coords(&mut self.coords)
+    }
+}
+
+/// Numbers reuse their scalar encoding in as many dimensions as it takes
+/// base 16 digits, two per byte.
+macro_rules! number_field {
+    ($($ty:ty),*) => {
+        $(
+            impl ToField for $ty {
+                #[inline]
+                fn write_coords(&self, coords: &mut Vec<u8>) {
+                    const LEN: usize = 2 * core::mem::size_of::<$ty>();
+                    coords.extend_from_slice(&ToCoords::<LEN>::to_coords(*self));
+                }
+            }
+
+            impl FromField for $ty {
+                #[inline]
+                fn read_coords(coords: &mut &[u8]) -> Self {
+                    const LEN: usize = 2 * core::mem::size_of::<$ty>();
+                    let len = LEN.min(coords.len());
+                    let mut digits = [0; LEN];
+                    digits[..len].copy_from_slice(&coords[..len]);
+                    *coords = &coords[len..];
+                    <$ty>::from_coords(digits)
+                }
+            }
+        )*
+    };
+}
+
+number_field!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64);
+
+impl ToField for str {
+    #[inline]
+    fn write_coords(&self, coords: &mut Vec<u8>) {
+        escaped(self.as_bytes(), coords);
+        coords.extend_from_slice(&[0, 0]);
+    }
+}
+
+impl ToField for String {
+    #[inline]
+    fn write_coords(&self, coords: &mut Vec<u8>) {
+        self.as_str().write_coords(coords)
+    }
+}
+
+impl<F: ToField + ?Sized> ToField for &F {
+    #[inline]
+    fn write_coords(&self, coords: &mut Vec<u8>) {
+        (**self).write_coords(coords)
+    }
+}
+
+impl FromField for String {
+    #[inline]
+    fn read_coords(coords: &mut &[u8]) -> Self {
+        let (bytes, len) = unescaped(coords);
+        *coords = &coords[len..];
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
+
+macro_rules! tuple_coords {
+    ($(($($name:ident),+)),*) => {
+        $(
+            impl<const DIM: usize, $($name: ToField),+> ToCoords<DIM> for ($($name,)+) {
+                #[inline]
+                fn to_coords(self) -> [u8; DIM] {
+                    #[allow(non_snake_case)]
+                    let ($($name,)+) = self;
+                    CoordsBuilder::new()$(.field($name))+.to_coords()
+                }
+
+                #[inline]
+                fn to_chunks(self) -> Vec<[u8; DIM]> {
+                    #[allow(non_snake_case)]
+                    let ($($name,)+) = self;
+                    CoordsBuilder::new()$(.field($name))+.to_chunks()
+                }
+            }
+
+            impl<const DIM: usize, $($name: FromField),+> FromCoords<DIM> for ($($name,)+) {
+                #[inline]
+                fn from_coords(coords: [u8; DIM]) -> Self {
+                    let mut reader = CoordsReader::new(&coords);
+                    ($(reader.field::<$name>(),)+)
+                }
+
+                #[inline]
+                fn from_chunks(chunks: &[[u8; DIM]]) -> Self {
+                    let coords = chunks.concat();
+                    let mut reader = CoordsReader::new(&coords);
+                    ($(reader.field::<$name>(),)+)
+                }
+            }
+        )*
+    };
+}
+
+tuple_coords!((A, B), (A, B, C), (A, B, C, D), (A, B, C, D, E));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunks<K: ToCoords<8>>(key: K) -> Vec<[u8; 8]> {
+        key.to_chunks()
+    }
+
+    #[test]
+    fn test_tuples() {
+        let coords: [u8; 16] = (0x12_u32, 0xab_u16).to_coords();
+        assert_eq!(coords, [0, 0, 0, 0, 0, 0, 1, 2, 0, 0, 0xa, 0xb, 0, 0, 0, 0]);
+
+        let keys = [
+            (-1_i32, 5_u32),
+            (0, 0),
+            (0, 1),
+            (0, u32::MAX),
+            (1, 0),
+            (i32::MAX, 0),
+        ];
+        for pair in keys.windows(2) {
+            assert!(chunks(pair[0]) < chunks(pair[1]));
+        }
+        for key in keys {
+            assert_eq!(<(i32, u32)>::from_chunks(&chunks(key)), key);
+        }
+    }
+
+    #[test]
+    fn test_strings() {
+        // Shorter strings sort first, regardless of the fields after them
+        let keys = [
+            (1_u16, "", 9_u64),
+            (1, "a", u64::MAX),
+            (1, "ab", 0),
+            (1, "ab", 1),
+            (1, "abc", 0),
+            (1, "b", 0),
+            (2, "", 0),
+        ];
+        for pair in keys.windows(2) {
+            assert!(
+                chunks(pair[0]) < chunks(pair[1]),
+                "{:?} {:?}",
+                pair[0],
+                pair[1]
+            );
+        }
+
+        for (a, b, c) in keys {
+            let key = <(u16, String, u64)>::from_chunks(&chunks((a, b, c)));
+            assert_eq!(key, (a, b.to_string(), c));
+        }
+
+        let key = (String::from("tenant"), 7_u8);
+        let coords: [u8; 16] = key.clone().to_coords();
+        assert_eq!(<(String, u8)>::from_coords(coords), key);
+    }
+
+    #[test]
+    fn test_zero_bytes() {
+        let keys = [
+            (1_u8, "a", u8::MAX),
+            (1, "a\0", 0),
+            (1, "a\0", 1),
+            (1, "a\0\0", 0),
+            (1, "a\0b", 0),
+            (1, "a\x01", 0),
+            (2, "\0", 0),
+        ];
+        for pair in keys.windows(2) {
+            assert!(
+                chunks(pair[0]) < chunks(pair[1]),
+                "{:?} {:?}",
+                pair[0],
+                pair[1]
+            );
+        }
+
+        for (a, b, c) in keys {
+            let key = <(u8, String, u8)>::from_chunks(&chunks((a, b, c)));
+            assert_eq!(key, (a, b.to_string(), c));
+        }
+    }
+
+    #[test]
+    fn test_builder() {
+        let builder = CoordsBuilder::new().field(3_u8).field("ab").field(-1.5_f64);
+        let tuple = (3_u8, "ab", -1.5_f64);
+        assert_eq!(chunks(builder.clone()), chunks(tuple));
+
+        let coords = builder.as_ref().to_vec();
+        let mut reader = CoordsReader::new(&coords);
+        assert_eq!(reader.field::<u8>(), 3);
+        assert_eq!(reader.field::<String>(), "ab");
+        assert_eq!(reader.field::<f64>(), -1.5);
+
+        // The leading fields are a prefix of the whole key
+        let prefix = CoordsBuilder::new().field(3_u8).field("ab");
+        assert!(coords.starts_with(prefix.as_ref()));
+    }
+}
@@ -0,0 +1,608 @@
+//! Encodings of keys into mdlist coordinates.
+//!
+//! The encodings are order-preserving: comparing two coordinate vectors
+//! lexicographically gives the same result as comparing the keys they were
+//! made from. Since the mdlist is walked in lexicographic coordinate order,
+//! this makes iteration return keys in their natural order.
+//!
+//! Integers and floats are split into digits, most significant digit first,
+//! one digit per dimension. The radix follows from `DIM`: digits are 1, 2, 4
+//! or 8 bits wide, whichever is the narrowest that fits the type in `DIM`
+//! dimensions. A `u64` is 16 base 16 digits with `DIM = 16`, 8 bytes with
+//! `DIM = 8` and 32 base 4 digits with `DIM = 32`. Wrapping a number in
+//! [`Radix`] picks the digit width instead. Numbers that take fewer than
+//! `DIM` digits are padded with zeros at the end, and types that do not fit
+//! in `DIM` bytes fail to compile.
+//!
+//! Fewer bits per digit make for a smaller fan-out per dimension but a
+//! deeper list, see the `mdlist` benchmarks.
+//!
+//! [`FromCoords`] is the inverse, used to hand keys back out of the list.
+//!
+//! Keys that do not fit in `DIM` dimensions are split into chunks of `DIM`
+//! coordinates, one for each level of nested lists. Comparing the chunks
+//! lexicographically, with a shorter key sorting before the keys it is a
+//! prefix of, again gives the order of the keys.
+
+pub trait ToCoords<const DIM: usize> {
+    fn to_coords(self) -> [u8; DIM];
+
+    /// Splits the key into one chunk of coordinates per level of nesting.
+    /// Keys that always fit in `DIM` dimensions have a single chunk.
+    #[inline]
+    fn to_chunks(self) -> Vec<[u8; DIM]>
+    where
+        Self: Sized,
+    {
+        vec![self.to_coords()]
+    }
+}
+
+pub trait FromCoords<const DIM: usize> {
+    fn from_coords(coords: [u8; DIM]) -> Self;
+
+    /// The inverse of [`ToCoords::to_chunks`].
+    #[inline]
+    fn from_chunks(chunks: &[[u8; DIM]]) -> Self
+    where
+        Self: Sized,
+    {
+        debug_assert_eq!(chunks.len(), 1);
+        Self::from_coords(chunks[0])
+    }
+}
+
+/// Bits per digit to fit `bytes` bytes in `dim` dimensions, out of 1, 2, 4
+/// and 8 so that no digit straddles two bytes.
+const fn digit_bits(bytes: usize, dim: usize) -> usize {
+    let mut bits = 1;
+    while bits < 8 && bits * dim < bytes * 8 {
+        bits *= 2;
+    }
+    radix_bits(bits, bytes, dim)
+}
+
+/// Checks that digits of `bits` bits fit `bytes` bytes in `dim` dimensions.
+const fn radix_bits(bits: usize, bytes: usize, dim: usize) -> usize {
+    assert!(
+        matches!(bits, 1 | 2 | 4 | 8),
+        "digits must be 1, 2, 4 or 8 bits wide"
+    );
+    assert!(
+        bits * dim >= bytes * 8,
+        "the type does not fit in DIM dimensions"
+    );
+    bits
+}
+
+/// Splits big-endian `bytes` into digits of `bits` bits, padding with zeros.
+#[inline]
+fn digits<const N: usize, const DIM: usize>(bytes: [u8; N], bits: usize) -> [u8; DIM] {
+    let per_byte = 8 / bits;
+    let mask = ((1_u16 << bits) - 1) as u8;
+
+    let mut coords = [0; DIM];
+    for (idx, byte) in bytes.iter().enumerate() {
+        for digit in 0..per_byte {
+            coords[idx * per_byte + digit] = (byte >> (8 - bits * (digit + 1))) & mask;
+        }
+    }
+    coords
+}
+
+/// Joins the leading digits of `coords` back into big-endian bytes.
+#[inline]
+fn bytes<const N: usize, const DIM: usize>(coords: [u8; DIM], bits: usize) -> [u8; N] {
+    let per_byte = 8 / bits;
+
+    let mut bytes = [0; N];
+    for (idx, byte) in bytes.iter_mut().enumerate() {
+        for digit in 0..per_byte {
+            *byte = ((*byte as u16) << bits) as u8 | coords[idx * per_byte + digit];
+        }
+    }
+    bytes
+}
+
+/// Numbers split into digits of a given width, in an order-preserving way.
+trait Digits: Sized {
+    const BYTES: usize;
+
+    fn to_digits<const DIM: usize>(self, bits: usize) -> [u8; DIM];
+
+    fn from_digits<const DIM: usize>(coords: [u8; DIM], bits: usize) -> Self;
+}
+
+/// A number encoded with digits of `BITS` bits, instead of the narrowest ones
+/// that fit in `DIM` dimensions. `BITS` is 1, 2, 4 or 8, and the dimensions
+/// past the digits are padded with zeros: a `Radix<u64, 4>` takes the first
+/// 16 of 32 dimensions, and sorts like the `u64` it wraps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Radix<K, const BITS: usize>(pub K);
+
+/// Pads coordinates with zeros to `DIM` dimensions, or cuts them off after
+/// `DIM`. Cutting keeps the order of keys, but not every key apart.
+#[inline]
+pub(super) fn padded<const DIM: usize>(coords: &[u8]) -> [u8; DIM] {
+    let len = coords.len().min(DIM);
+    let mut padded = [0; DIM];
+    padded[..len].copy_from_slice(&coords[..len]);
+    padded
+}
+
+/// Splits coordinates into chunks of `DIM`, only the last one is padded.
+#[inline]
+pub(super) fn chunked<const DIM: usize>(coords: &[u8]) -> Vec<[u8; DIM]> {
+    if coords.is_empty() {
+        return vec![[0; DIM]];
+    }
+    coords.chunks(DIM).map(padded).collect()
+}
+
+/// Escapes the zero bytes of a byte string as `0x00 0xff`, so that no zero
+/// byte of the key can be taken for padding or a terminator.
+#[inline]
+pub(super) fn escaped(bytes: &[u8], coords: &mut Vec<u8>) {
+    for &b in bytes {
+        coords.push(b);
+        if b == 0 {
+            coords.push(0xff);
+        }
+    }
+}
+
+/// Reads an escaped byte string from the front of `coords` up to its
+/// terminator, or to the end. Returns the string and how many coordinates
+/// it took, terminator included.
+#[inline]
+pub(super) fn unescaped(coords: &[u8]) -> (Vec<u8>, usize) {
+    let mut bytes = vec![];
+    let mut idx = 0;
+    while idx < coords.len() {
+        match (coords[idx], coords.get(idx + 1)) {
+            (0, Some(0xff)) => bytes.push(0),
+            (0, _) => return (bytes, (idx + 2).min(coords.len())),
+            (b, _) => {
+                bytes.push(b);
+                idx += 1;
+                continue;
+            }
+        }
+        idx += 2;
+    }
+    (bytes, idx)
+}
+
+/// Escapes a byte string key, see the impl of [`ToCoords`] for `&[u8]`.
+#[inline]
+fn encoded(bytes: &[u8]) -> Vec<u8> {
+    let mut coords = Vec::with_capacity(bytes.len());
+    escaped(bytes, &mut coords);
+    coords
+}
+
+/// Joins the chunks of a byte string key and decodes it.
+#[inline]
+fn decoded<const DIM: usize>(chunks: &[[u8; DIM]]) -> Vec<u8> {
+    unescaped(&chunks.concat()).0
+}
+
+macro_rules! unsigned_digits {
+    ($($ty:ty),*) => {
+        $(
+            impl Digits for $ty {
+                const BYTES: usize = core::mem::size_of::<$ty>();
+
+                #[inline]
+                fn to_digits<const DIM: usize>(self, bits: usize) -> [u8; DIM] {
+                    digits(self.to_be_bytes(), bits)
+                }
+
+                #[inline]
+                fn from_digits<const DIM: usize>(coords: [u8; DIM], bits: usize) -> Self {
+                    <$ty>::from_be_bytes(bytes(coords, bits))
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! signed_digits {
+    ($($ty:ty => $unsigned:ty),*) => {
+        $(
+            impl Digits for $ty {
+                const BYTES: usize = core::mem::size_of::<$ty>();
+
+                /// Flips the sign bit, which moves negative numbers below
+                /// positive ones while keeping two's complement order within
+                /// each of them.
+                #[inline]
+                fn to_digits<const DIM: usize>(self, bits: usize) -> [u8; DIM] {
+                    let flipped = self as $unsigned ^ (1 << (<$unsigned>::BITS - 1));
+                    digits(flipped.to_be_bytes(), bits)
+                }
+
+                #[inline]
+                fn from_digits<const DIM: usize>(coords: [u8; DIM], bits: usize) -> Self {
+                    let flipped = <$unsigned>::from_be_bytes(bytes(coords, bits));
+                    (flipped ^ (1 << (<$unsigned>::BITS - 1))) as $ty
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! float_digits {
+    ($($ty:ty => $bits:ty),*) => {
+        $(
+            impl Digits for $ty {
+                const BYTES: usize = core::mem::size_of::<$ty>();
+
+                /// Negative floats have all their bits flipped, so that larger
+                /// magnitudes sort first, positive floats only the sign bit.
+                /// This is the IEEE-754 total order, so `-0.0` sorts below
+                /// `0.0` and positive NaNs sort above infinity.
+                #[inline]
+                fn to_digits<const DIM: usize>(self, bits: usize) -> [u8; DIM] {
+                    let raw = self.to_bits();
+                    let sign: $bits = 1 << (<$bits>::BITS - 1);
+                    let raw = if raw & sign != 0 { !raw } else { raw | sign };
+                    digits(raw.to_be_bytes(), bits)
+                }
+
+                #[inline]
+                fn from_digits<const DIM: usize>(coords: [u8; DIM], bits: usize) -> Self {
+                    let raw = <$bits>::from_be_bytes(bytes(coords, bits));
+                    let sign: $bits = 1 << (<$bits>::BITS - 1);
+                    <$ty>::from_bits(if raw & sign != 0 { raw & !sign } else { !raw })
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! number_to_coords {
+    ($($ty:ty),*) => {
+        $(
+            impl<const DIM: usize> ToCoords<DIM> for $ty {
+                #[inline]
+                fn to_coords(self) -> [u8; DIM] {
+                    self.to_digits(const { digit_bits(<$ty>::BYTES, DIM) })
+                }
+            }
+
+            impl<const DIM: usize> FromCoords<DIM> for $ty {
+                #[inline]
+                fn from_coords(coords: [u8; DIM]) -> Self {
+                    <$ty>::from_digits(coords, const { digit_bits(<$ty>::BYTES, DIM) })
+                }
+            }
+
+            impl<const BITS: usize, const DIM: usize> ToCoords<DIM> for Radix<$ty, BITS> {
+                #[inline]
+                fn to_coords(self) -> [u8; DIM] {
+                    self.0.to_digits(const { radix_bits(BITS, <$ty>::BYTES, DIM) })
+                }
+            }
+
+            impl<const BITS: usize, const DIM: usize> FromCoords<DIM> for Radix<$ty, BITS> {
+                #[inline]
+                fn from_coords(coords: [u8; DIM]) -> Self {
+                    Radix(<$ty>::from_digits(coords, const { radix_bits(BITS, <$ty>::BYTES, DIM) }))
+                }
+            }
+        )*
+    };
+}
+
+unsigned_digits!(u8, u16, u32, u64, usize);
+signed_digits!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, isize => usize);
+float_digits!(f32 => u32, f64 => u64);
+number_to_coords!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64);
+
+/// Byte strings use one dimension per byte, two for a zero byte, and are
+/// padded with zeros. Strings that take more than `DIM` dimensions are split
+/// into several chunks, [`ToCoords::to_coords`] keeps only the first one.
+///
+/// Zero bytes are escaped as `0x00 0xff`, so the padding can be told apart
+/// from them and `"a"` sorts before `"a\0"`, which sorts before `"a\x01"`.
+/// The padding then ends the key the way a `0x00 0x00` terminator would,
+/// without taking up a dimension of its own.
+impl<const DIM: usize> ToCoords<DIM> for &[u8] {
+    #[inline]
+    fn to_coords(self) -> [u8; DIM] {
+        padded(&encoded(self))
+    }
+
+    #[inline]
+    fn to_chunks(self) -> Vec<[u8; DIM]> {
+        chunked(&encoded(self))
+    }
+}
+
+impl<const DIM: usize> ToCoords<DIM> for &str {
+    #[inline]
+    fn to_coords(self) -> [u8; DIM] {
+        self.as_bytes().to_coords()
+    }
+
+    #[inline]
+    fn to_chunks(self) -> Vec<[u8; DIM]> {
+        self.as_bytes().to_chunks()
+    }
+}
+
+impl<const DIM: usize> ToCoords<DIM> for Vec<u8> {
+    #[inline]
+    fn to_coords(self) -> [u8; DIM] {
+        self.as_slice().to_coords()
+    }
+
+    #[inline]
+    fn to_chunks(self) -> Vec<[u8; DIM]> {
+        self.as_slice().to_chunks()
+    }
+}
+
+impl<const DIM: usize> ToCoords<DIM> for String {
+    #[inline]
+    fn to_coords(self) -> [u8; DIM] {
+        self.as_bytes().to_coords()
+    }
+
+    #[inline]
+    fn to_chunks(self) -> Vec<[u8; DIM]> {
+        self.as_bytes().to_chunks()
+    }
+}
+
+impl<const DIM: usize> ToCoords<DIM> for [u8; DIM] {
+    #[inline]
+    fn to_coords(self) -> [u8; DIM] {
+        self
+    }
+}
+
+impl<const DIM: usize> FromCoords<DIM> for Vec<u8> {
+    #[inline]
+    fn from_coords(coords: [u8; DIM]) -> Self {
+        decoded(&[coords])
+    }
+
+    #[inline]
+    fn from_chunks(chunks: &[[u8; DIM]]) -> Self {
+        decoded(chunks)
+    }
+}
+
+impl<const DIM: usize> FromCoords<DIM> for String {
+    #[inline]
+    fn from_coords(coords: [u8; DIM]) -> Self {
+        String::from_utf8_lossy(&decoded(&[coords])).into_owned()
+    }
+
+    #[inline]
+    fn from_chunks(chunks: &[[u8; DIM]]) -> Self {
+        String::from_utf8_lossy(&decoded(chunks)).into_owned()
+    }
+}
+
+impl<const DIM: usize> FromCoords<DIM> for [u8; DIM] {
+    #[inline]
+    fn from_coords(coords: [u8; DIM]) -> Self {
+        coords
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_ordered<K>(keys: &[K])
+    where
+        K: Copy + PartialOrd + std::fmt::Debug + ToCoords<16> + FromCoords<16>,
+    {
+        for key in keys {
+            assert_eq!(K::from_coords(key.to_coords()), *key);
+        }
+
+        for pair in keys.windows(2) {
+            assert!(pair[0] < pair[1]);
+            assert!(
+                pair[0].to_coords() < pair[1].to_coords(),
+                "{:?} should sort before {:?}",
+                pair[0],
+                pair[1]
+            );
+        }
+    }
+
+    #[test]
+    fn test_unsigned() {
+        assert_eq!(
+            0x0123_4567_89ab_cdef_u64.to_coords(),
+            [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]
+        );
+        assert_ordered(&[0_u64, 1, 15, 16, 255, 256, 1 << 32, u64::MAX]);
+        assert_ordered(&[0_u16, 1, 0xff, 0x100, u16::MAX]);
+    }
+
+    #[test]
+    fn test_signed() {
+        assert_ordered(&[i64::MIN, -256, -1, 0, 1, 256, i64::MAX]);
+        assert_ordered(&[i32::MIN, -16, -15, -1, 0, 1, 15, 16, i32::MAX]);
+        assert_ordered(&[i8::MIN, -1, 0, 1, i8::MAX]);
+    }
+
+    #[test]
+    fn test_float() {
+        assert_ordered(&[
+            f64::NEG_INFINITY,
+            f64::MIN,
+            -1.5,
+            -f64::MIN_POSITIVE,
+            0.0,
+            f64::MIN_POSITIVE,
+            1.0,
+            1.5,
+            f64::MAX,
+            f64::INFINITY,
+        ]);
+        assert_ordered(&[f32::NEG_INFINITY, -2.0, -0.5, 0.5, 2.0, f32::INFINITY]);
+        let (neg, pos): ([u8; 16], [u8; 16]) = ((-0.0_f64).to_coords(), 0.0_f64.to_coords());
+        assert!(neg < pos);
+    }
+
+    fn assert_ordered_in<K, const DIM: usize>(keys: &[K])
+    where
+        K: Copy + PartialOrd + std::fmt::Debug + ToCoords<DIM> + FromCoords<DIM>,
+    {
+        for key in keys {
+            assert_eq!(K::from_coords(key.to_coords()), *key);
+        }
+
+        for pair in keys.windows(2) {
+            let (lo, hi): ([u8; DIM], [u8; DIM]) = (pair[0].to_coords(), pair[1].to_coords());
+            assert!(lo < hi, "{:?} should sort before {:?}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn test_radix() {
+        let key = 0x0123_4567_89ab_cdef_u64;
+        let coords: [u8; 8] = key.to_coords();
+        assert_eq!(coords, [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef]);
+        let coords: [u8; 32] = key.to_coords();
+        assert_eq!(coords[..8], [0, 0, 0, 1, 0, 2, 0, 3]);
+        let coords: [u8; 64] = key.to_coords();
+        assert_eq!(coords[..8], [0, 0, 0, 0, 0, 0, 0, 1]);
+
+        // The narrowest digits that fit, padded at the end
+        let coords: [u8; 8] = 0xabcd_u16.to_coords();
+        assert_eq!(coords, [2, 2, 2, 3, 3, 0, 3, 1]);
+        let coords: [u8; 4] = 0xabcd_u16.to_coords();
+        assert_eq!(coords, [0xa, 0xb, 0xc, 0xd]);
+        let coords: [u8; 12] = 0xabcd_u16.to_coords();
+        assert_eq!(coords, [2, 2, 2, 3, 3, 0, 3, 1, 0, 0, 0, 0]);
+
+        let unsigned = [0_u64, 1, 3, 4, 255, 256, 1 << 32, u64::MAX];
+        assert_ordered_in::<_, 8>(&unsigned);
+        assert_ordered_in::<_, 32>(&unsigned);
+        assert_ordered_in::<_, 64>(&unsigned);
+
+        let signed = [i32::MIN, -4, -3, -1, 0, 1, 3, 4, i32::MAX];
+        assert_ordered_in::<_, 4>(&signed);
+        assert_ordered_in::<_, 16>(&signed);
+        assert_ordered_in::<_, 32>(&signed);
+
+        let floats = [f64::NEG_INFINITY, -1.5, 0.0, 1.0, 1.5, f64::INFINITY];
+        assert_ordered_in::<_, 8>(&floats);
+        assert_ordered_in::<_, 64>(&floats);
+        // A chosen radix, padded at the end too
+        let coords: [u8; 32] = Radix::<_, 4>(key).to_coords();
+        assert_eq!(
+            coords[..16],
+            [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]
+        );
+        assert_eq!(coords[16..], [0; 16]);
+        let coords: [u8; 16] = Radix::<_, 8>(0xabcd_u16).to_coords();
+        assert_eq!(coords[..3], [0xab, 0xcd, 0]);
+
+        let radix = unsigned.map(Radix::<_, 4>);
+        assert_ordered_in::<_, 16>(&radix);
+        assert_ordered_in::<_, 32>(&radix);
+        let radix = signed.map(Radix::<_, 8>);
+        assert_ordered_in::<_, 4>(&radix);
+        assert_ordered_in::<_, 16>(&radix);
+    }
+
+    #[test]
+    fn test_bytes() {
+        let coords: [u8; 4] = "ab".to_coords();
+        assert_eq!(coords, [b'a', b'b', 0, 0]);
+
+        let keys = ["", "a", "ab", "abc", "b", "ba"];
+        for pair in keys.windows(2) {
+            let (lo, hi): ([u8; 4], [u8; 4]) = (pair[0].to_coords(), pair[1].to_coords());
+            assert!(lo < hi);
+        }
+
+        for key in keys {
+            let coords: [u8; 4] = key.to_coords();
+            assert_eq!(String::from_coords(coords), key);
+            assert_eq!(Vec::from_coords(coords), key.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_zero_bytes() {
+        let chunks: Vec<[u8; 4]> = "a\0b\0".to_chunks();
+        assert_eq!(chunks, [[b'a', 0, 0xff, b'b'], [0, 0xff, 0, 0]]);
+
+        let keys = [
+            "", "\0", "\0\0", "\0a", "a", "a\0", "a\0\0", "a\0b", "a\x01", "abc", "abc\0", "abcd",
+            "abcd\0",
+        ];
+        for pair in keys.windows(2) {
+            let (lo, hi): (Vec<[u8; 4]>, Vec<[u8; 4]>) = (pair[0].to_chunks(), pair[1].to_chunks());
+            assert!(lo < hi, "{:?} should sort before {:?}", pair[0], pair[1]);
+        }
+
+        for key in keys {
+            let chunks: Vec<[u8; 4]> = key.to_chunks();
+            assert_eq!(String::from_chunks(&chunks), key);
+            assert_eq!(Vec::from_chunks(&chunks), key.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_cut_off() {
+        // A single chunk keeps the order, but not every key apart
+        let keys = ["abc", "abcd", "abcde", "abd"];
+        for pair in keys.windows(2) {
+            let (lo, hi): ([u8; 4], [u8; 4]) = (pair[0].to_coords(), pair[1].to_coords());
+            assert!(
+                lo <= hi,
+                "{:?} should not sort after {:?}",
+                pair[0],
+                pair[1]
+            );
+        }
+        let coords: [u8; 4] = "abcde".to_coords();
+        assert_eq!(coords, *b"abcd");
+        assert_eq!(String::from_coords(coords), "abcd");
+    }
+
+    #[test]
+    fn test_chunks() {
+        let chunks: Vec<[u8; 4]> = "abcdef".to_chunks();
+        assert_eq!(chunks, [*b"abcd", [b'e', b'f', 0, 0]]);
+        let chunks: Vec<[u8; 4]> = "".to_chunks();
+        assert_eq!(chunks, [[0; 4]]);
+
+        let keys = [
+            "",
+            "a",
+            "abc",
+            "abcd",
+            "abcda",
+            "abcdabcd",
+            "abcdabcda",
+            "abd",
+            "b",
+        ];
+        for pair in keys.windows(2) {
+            let (lo, hi): (Vec<[u8; 4]>, Vec<[u8; 4]>) = (pair[0].to_chunks(), pair[1].to_chunks());
+            assert!(lo < hi, "{:?} should sort before {:?}", pair[0], pair[1]);
+        }
+
+        for key in keys {
+            let chunks: Vec<[u8; 4]> = key.to_chunks();
+            assert_eq!(String::from_chunks(&chunks), key);
+            assert_eq!(Vec::from_chunks(&chunks), key.as_bytes());
+        }
+
+        let chunks: Vec<[u8; 16]> = 42_u64.to_chunks();
+        assert_eq!(u64::from_chunks(&chunks), 42);
+    }
+}
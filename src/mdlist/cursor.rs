@@ -0,0 +1,152 @@
+//! A repositionable view into an [`MdList`], see [`MdList::cursor`].
+
+use core::ops::Bound;
+
+use super::{Entries, FromCoords, MdList, ToCoords};
+use crate::ebr::Guard;
+
+/// Where a [`Cursor`] is, in between or at the entries of the map.
+enum Position<'g, const DIM: usize, T> {
+    /// Before the first entry
+    Start,
+    At(Vec<[u8; DIM]>, &'g T),
+    /// After the last entry
+    End,
+}
+
+/// Walks an [`MdList`] in both directions, and jumps to any key.
+///
+/// The cursor remembers the key it is at rather than the node, so the entry
+/// may be removed or replaced while the cursor is at it. Turning around
+/// locates the key again, following the same path as `List::locate_pred`,
+/// and continues from there. Steps in the same direction reuse the
+/// traversal of the previous step. Like the iterators of the map, a cursor
+/// is weakly consistent: entries inserted or removed concurrently may or may
+/// not be visited.
+///
+/// A cursor starts before the first entry, and falls off either end of the
+/// map when it runs out of entries in that direction. Going forward it is an
+/// [`Iterator`], so a page of results is `cursor.by_ref().take(n)`, and the
+/// last key of a page makes a continuation token for
+/// [`Cursor::seek_after`].
+pub struct Cursor<'g, K, T, const DIM: usize> {
+    map: &'g MdList<K, T, DIM>,
+    guard: &'g Guard,
+    position: Position<'g, DIM, T>,
+    // The traversal that led to `position`, from either side
+    ahead: Option<Entries<'g, DIM, T>>,
+    behind: Option<Entries<'g, DIM, T>>,
+}
+
+impl<'g, K, T, const DIM: usize> Cursor<'g, K, T, DIM>
+where
+    K: FromCoords<DIM>,
+{
+    pub(super) fn new(map: &'g MdList<K, T, DIM>, guard: &'g Guard) -> Self {
+        Self {
+            map,
+            guard,
+            position: Position::Start,
+            ahead: None,
+            behind: None,
+        }
+    }
+
+    /// Returns the entry the cursor is at, if it is not past either end.
+    pub fn current(&self) -> Option<(K, &'g T)> {
+        match &self.position {
+            Position::At(chunks, value) => Some((K::from_chunks(chunks), *value)),
+            _ => None,
+        }
+    }
+
+    /// Moves to the first entry with a key of at least `key`.
+    pub fn seek<Q: ToCoords<DIM>>(&mut self, key: Q) -> Option<(K, &'g T)> {
+        self.seek_from(Bound::Included(key.to_chunks()))
+    }
+
+    /// Moves to the first entry with a key past `key`.
+    pub fn seek_after<Q: ToCoords<DIM>>(&mut self, key: Q) -> Option<(K, &'g T)> {
+        self.seek_from(Bound::Excluded(key.to_chunks()))
+    }
+
+    pub fn seek_to_first(&mut self) -> Option<(K, &'g T)> {
+        self.seek_from(Bound::Unbounded)
+    }
+
+    pub fn seek_to_last(&mut self) -> Option<(K, &'g T)> {
+        self.position = Position::End;
+        self.ahead = None;
+        self.behind = None;
+        self.prev()
+    }
+
+    fn seek_from(&mut self, lo: Bound<Vec<[u8; DIM]>>) -> Option<(K, &'g T)> {
+        self.position = Position::Start;
+        self.ahead = Some(self.map.chunks_range(lo, Bound::Unbounded, self.guard));
+        self.behind = None;
+        self.next()
+    }
+
+    /// Moves to the entry before the current one. Before the first entry,
+    /// the cursor stays there.
+    pub fn prev(&mut self) -> Option<(K, &'g T)> {
+        let mut behind = match (self.behind.take(), &self.position) {
+            (Some(behind), _) => behind,
+            (None, Position::Start) => return None,
+            (None, Position::At(chunks, _)) => {
+                let hi = Bound::Excluded(chunks.clone());
+                self.map.chunks_range(Bound::Unbounded, hi, self.guard)
+            }
+            (None, Position::End) => {
+                self.map
+                    .chunks_range(Bound::Unbounded, Bound::Unbounded, self.guard)
+            }
+        };
+        self.ahead = None;
+        if self.land(behind.next_back(), Position::Start) {
+            self.behind = Some(behind);
+        }
+        self.current()
+    }
+
+    /// Moves to `entry`, or to `end` if there is none. Returns whether
+    /// there may be more entries past it.
+    #[inline]
+    fn land(&mut self, entry: Option<(Vec<[u8; DIM]>, &'g T)>, end: Position<'g, DIM, T>) -> bool {
+        self.position = match entry {
+            Some((chunks, value)) => Position::At(chunks, value),
+            None => end,
+        };
+        !matches!(self.position, Position::Start | Position::End)
+    }
+}
+
+impl<'g, K, T, const DIM: usize> Iterator for Cursor<'g, K, T, DIM>
+where
+    K: FromCoords<DIM>,
+{
+    type Item = (K, &'g T);
+
+    /// Moves to the entry after the current one. After the last entry, the
+    /// cursor stays there.
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut ahead = match (self.ahead.take(), &self.position) {
+            (Some(ahead), _) => ahead,
+            (None, Position::End) => return None,
+            (None, Position::At(chunks, _)) => {
+                let lo = Bound::Excluded(chunks.clone());
+                self.map.chunks_range(lo, Bound::Unbounded, self.guard)
+            }
+            (None, Position::Start) => {
+                self.map
+                    .chunks_range(Bound::Unbounded, Bound::Unbounded, self.guard)
+            }
+        };
+        self.behind = None;
+        if self.land(ahead.next(), Position::End) {
+            self.ahead = Some(ahead);
+        }
+        self.current()
+    }
+}
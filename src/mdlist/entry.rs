@@ -0,0 +1,174 @@
+//! A view into a single key of an [`MdList`], see [`MdList::entry`].
+
+use core::ops::Deref;
+
+use super::{Alloc, IsElement, MdList, NodeWithValue, Pred};
+use crate::ebr::Guard;
+
+/// A key of an [`MdList`] that was looked up once, which is either occupied
+/// or vacant.
+///
+/// The handles keep the position that the lookup found. Inserting through a
+/// [`VacantEntry`] first tries the CAS at that position and only locates the
+/// key again if the CAS fails, so a lookup followed by an insert costs a
+/// single traversal in the common case.
+///
+/// Other threads keep going in the meantime, so an entry may be out of date
+/// by the time it is used. The methods that modify the map say how they deal
+/// with that.
+pub enum Entry<'g, K, T, const DIM: usize> {
+    Occupied(OccupiedEntry<'g, K, T, DIM>),
+    Vacant(VacantEntry<'g, K, T, DIM>),
+}
+
+/// An [`Entry`] for a key that had a value.
+pub struct OccupiedEntry<'g, K, T, const DIM: usize> {
+    level: &'g MdList<K, T, DIM>,
+    coords: [u8; DIM],
+    node: &'g NodeWithValue<DIM, T>,
+    guard: &'g Guard,
+}
+
+/// An [`Entry`] for a key that had no value.
+pub struct VacantEntry<'g, K, T, const DIM: usize> {
+    map: &'g MdList<K, T, DIM>,
+    chunks: Vec<[u8; DIM]>,
+    // The nested map that holds the last chunk of the key, along with the
+    // position found in it. `None` if that map does not exist yet.
+    pred: Option<(&'g MdList<K, T, DIM>, Pred<'g, DIM>)>,
+    guard: &'g Guard,
+}
+
+impl<'g, K, T, const DIM: usize> Entry<'g, K, T, DIM> {
+    /// Returns the value at the key, inserting `value` if there is none.
+    #[inline]
+    pub fn or_insert(self, value: T) -> &'g T {
+        self.or_insert_with(|| value)
+    }
+
+    /// Returns the value at the key, inserting the result of `f` if there is
+    /// none. If another thread inserts a value first, that value is returned
+    /// and ours is dropped.
+    #[inline]
+    pub fn or_insert_with<F: FnOnce() -> T>(self, f: F) -> &'g T {
+        match self {
+            Entry::Occupied(entry) => entry.get(),
+            Entry::Vacant(entry) => match entry.insert(f()) {
+                Ok(value) | Err(value) => value,
+            },
+        }
+    }
+
+    #[inline]
+    pub fn or_default(self) -> &'g T
+    where
+        T: Default,
+    {
+        self.or_insert_with(T::default)
+    }
+}
+
+impl<'g, K, T, const DIM: usize> OccupiedEntry<'g, K, T, DIM> {
+    pub(super) fn new(
+        level: &'g MdList<K, T, DIM>,
+        coords: [u8; DIM],
+        node: &'g NodeWithValue<DIM, T>,
+        guard: &'g Guard,
+    ) -> Self {
+        Self {
+            level,
+            coords,
+            node,
+            guard,
+        }
+    }
+
+    /// Returns the value that was found, which stays valid for as long as
+    /// the guard is pinned, even if it is replaced in the meantime.
+    #[inline]
+    pub fn get(&self) -> &'g T {
+        self.node.deref()
+    }
+
+    /// Replaces the value if it is still the one that was found. Returns the
+    /// replaced value, or the present value (if any) in which case `value` is
+    /// dropped.
+    pub fn replace(self, value: T) -> Result<&'g T, Option<&'g T>> {
+        let guard = self.guard;
+        unsafe {
+            let elem = Alloc::alloc(NodeWithValue::new(self.coords, value), guard);
+            let swapped = self.level.list.insert_with(elem, guard, |present| {
+                present.is_some_and(|present| core::ptr::eq(present, self.node))
+            });
+            match swapped {
+                Ok(_) => Ok(self.get()),
+                Err(present) => {
+                    Alloc::dealloc(elem);
+                    Err(present.map(|v| v.deref()))
+                }
+            }
+        }
+    }
+
+    /// Removes the value if it is still the one that was found. Returns the
+    /// removed value, or the present value (if any) which stays in place.
+    pub fn remove(self) -> Result<&'g T, Option<&'g T>> {
+        let mut present = None;
+        unsafe {
+            let removed = self.level.list.remove_if(self.coords, self.guard, |p| {
+                let found = core::ptr::eq(p, self.node);
+                present = (!found).then_some(p);
+                found
+            });
+            match removed {
+                Some(_) => Ok(self.get()),
+                None => Err(present.map(|v| v.deref())),
+            }
+        }
+    }
+}
+
+impl<'g, K, T, const DIM: usize> VacantEntry<'g, K, T, DIM> {
+    pub(super) fn new(
+        map: &'g MdList<K, T, DIM>,
+        chunks: Vec<[u8; DIM]>,
+        pred: Option<(&'g MdList<K, T, DIM>, Pred<'g, DIM>)>,
+        guard: &'g Guard,
+    ) -> Self {
+        Self {
+            map,
+            chunks,
+            pred,
+            guard,
+        }
+    }
+
+    /// Inserts `value` unless another thread inserted a value in the
+    /// meantime. Returns the inserted value, or the present one in which case
+    /// `value` is dropped.
+    pub fn insert(self, value: T) -> Result<&'g T, &'g T> {
+        let guard = self.guard;
+        let (last, init) = self.chunks.split_last().unwrap();
+        unsafe {
+            let elem = Alloc::alloc(NodeWithValue::new(*last, value), guard);
+            let inserted = match self.pred {
+                Some((level, p)) => level
+                    .list
+                    .insert_at(elem, p, guard, |present| present.is_none()),
+                None => {
+                    let level = self.map.nested_or_insert(init, guard);
+                    level
+                        .list
+                        .insert_with(elem, guard, |present| present.is_none())
+                }
+            };
+            match inserted {
+                Ok(_) => Ok(elem.deref().deref()),
+                Err(present) => {
+                    Alloc::dealloc(elem);
+                    Err(present.unwrap().deref())
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,406 @@
+//! A map that places keys by their hash instead of their encoding, for
+//! tables that only ever look up single keys.
+//!
+//! With order-preserving encodings the shape of the list follows the keys.
+//! Byte strings take a dimension per byte with up to 256 siblings in it, and
+//! skewed keys, such as `user#0001`, `user#0002` and so on, crowd into a few
+//! subtrees. Hashing spreads the keys evenly over all the coordinates
+//! instead, so every lookup costs about the same whatever the keys look
+//! like. It comes at the cost of their order: there are no range or prefix
+//! queries on a [`HashedMdList`]. Which one is faster depends on the keys,
+//! see the `bench_hashed` benchmark.
+
+use core::hash::{BuildHasher, BuildHasherDefault, Hash, Hasher};
+use std::borrow::Borrow;
+use std::sync::Arc;
+
+use super::{Alloc, IsElement, NodeWithValue, ToCoords, Values};
+use crate::ebr::{pin, Guard};
+
+/// The entries whose keys hash to the same coordinates. There is almost
+/// always a single one, but the full keys still have to be compared.
+///
+/// Buckets are never modified in place, a changed bucket replaces the old one
+/// in the list. The entries are shared between the two, so the keys and
+/// values need not be `Clone`.
+type Bucket<K, V> = Vec<Arc<(K, V)>>;
+
+type BucketNode<K, V, const DIM: usize> = NodeWithValue<DIM, Bucket<K, V>>;
+
+/// FxHash, the hash function of rustc, on 64-bit little-endian words.
+///
+/// Unlike the hasher of the standard library, whose algorithm may change
+/// between releases, it is specified here, so a key hashes the same in every
+/// build and on every platform. It is fast on short keys such as integers and
+/// identifiers, but makes no attempt to resist keys chosen to collide.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FxHasher {
+    hash: u64,
+}
+
+impl FxHasher {
+    const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+    #[inline]
+    fn add(&mut self, word: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(Self::SEED);
+    }
+}
+
+impl Hasher for FxHasher {
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        let mut words = bytes.chunks_exact(8);
+        for word in &mut words {
+            self.add(u64::from_le_bytes(word.try_into().unwrap()));
+        }
+        let mut rest = words.remainder();
+        if rest.len() >= 4 {
+            self.add(u32::from_le_bytes(rest[..4].try_into().unwrap()) as u64);
+            rest = &rest[4..];
+        }
+        if rest.len() >= 2 {
+            self.add(u16::from_le_bytes(rest[..2].try_into().unwrap()) as u64);
+            rest = &rest[2..];
+        }
+        if let Some(&byte) = rest.first() {
+            self.add(byte as u64);
+        }
+    }
+
+    #[inline]
+    fn write_u8(&mut self, i: u8) {
+        self.add(i as u64);
+    }
+
+    #[inline]
+    fn write_u16(&mut self, i: u16) {
+        self.add(i as u64);
+    }
+
+    #[inline]
+    fn write_u32(&mut self, i: u32) {
+        self.add(i as u64);
+    }
+
+    #[inline]
+    fn write_u64(&mut self, i: u64) {
+        self.add(i);
+    }
+
+    #[inline]
+    fn write_usize(&mut self, i: usize) {
+        self.add(i as u64);
+    }
+
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// A concurrent map keyed by the hash of `K`, which is encoded as a `u64` in
+/// `DIM` dimensions, see [`super::coords`].
+///
+/// The default hasher is [`FxHasher`], so a key maps to the same coordinates
+/// in every run of every build. It is not protected against keys chosen to
+/// collide, use a randomly seeded `S` such as `RandomState` if keys come from
+/// untrusted input.
+pub struct HashedMdList<K, V, const DIM: usize = 16, S = BuildHasherDefault<FxHasher>> {
+    pub(super) list: Values<DIM, Bucket<K, V>>,
+    hasher: S,
+}
+
+impl<K, V, const DIM: usize, S: Default> Default for HashedMdList<K, V, DIM, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K, V, const DIM: usize> HashedMdList<K, V, DIM> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K, V, const DIM: usize, S> HashedMdList<K, V, DIM, S> {
+    pub fn with_hasher(hasher: S) -> Self {
+        Self {
+            list: Values::new(),
+            hasher,
+        }
+    }
+}
+
+impl<K, V, const DIM: usize, S> HashedMdList<K, V, DIM, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    u64: ToCoords<DIM>,
+{
+    #[inline]
+    pub(super) fn coords<Q: ?Sized + Hash>(&self, key: &Q) -> [u8; DIM] {
+        self.hasher.hash_one(key).to_coords()
+    }
+
+    #[inline]
+    fn find<'a, Q>(bucket: &'a Bucket<K, V>, key: &Q) -> Option<&'a Arc<(K, V)>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq,
+    {
+        bucket.iter().find(|entry| entry.0.borrow() == key)
+    }
+
+    /// Replaces `current`, the bucket at `coords` or `None` if there was
+    /// none, with `bucket`. Returns the new bucket, or the present one if
+    /// `current` has changed in the meantime, in which case `bucket` is
+    /// dropped.
+    fn replace<'g>(
+        &'g self,
+        coords: [u8; DIM],
+        current: Option<&'g BucketNode<K, V, DIM>>,
+        bucket: Bucket<K, V>,
+        guard: &'g Guard,
+    ) -> Result<&'g Bucket<K, V>, Option<&'g BucketNode<K, V, DIM>>> {
+        unsafe {
+            let elem = Alloc::alloc(NodeWithValue::new(coords, bucket), guard);
+            let swapped = self
+                .list
+                .insert_with(elem, guard, |present| match (present, current) {
+                    (Some(present), Some(current)) => core::ptr::eq(present, current),
+                    (None, None) => true,
+                    _ => false,
+                });
+            match swapped {
+                Ok(_) => Ok(&elem.deref().value),
+                Err(present) => {
+                    Alloc::dealloc(elem);
+                    Err(present)
+                }
+            }
+        }
+    }
+
+    /// Inserts `value` at `key`, replacing any previous value.
+    ///
+    /// A replaced value is reclaimed once no pinned thread can observe it.
+    pub fn insert(&self, key: K, value: V) {
+        let guard = &pin();
+        let coords = self.coords(&key);
+        let entry = Arc::new((key, value));
+
+        let mut current = unsafe { self.list.get(coords, guard) };
+        loop {
+            let mut bucket = current.map_or_else(Vec::new, |current| {
+                current
+                    .iter()
+                    .filter(|other| other.0 != entry.0)
+                    .cloned()
+                    .collect()
+            });
+            bucket.push(entry.clone());
+
+            match self.replace(coords, current, bucket, guard) {
+                Ok(_) => return,
+                Err(present) => current = present,
+            }
+        }
+    }
+
+    /// Inserts `value` at `key` unless a value is present. Returns the
+    /// inserted value, or the present one in which case `value` is dropped.
+    pub fn insert_if_absent<'g>(
+        &'g self,
+        key: K,
+        value: V,
+        guard: &'g Guard,
+    ) -> Result<&'g V, &'g V> {
+        let coords = self.coords(&key);
+        let entry = Arc::new((key, value));
+
+        let mut current = unsafe { self.list.get(coords, guard) };
+        loop {
+            let mut bucket = match current {
+                Some(current) => {
+                    if let Some(present) = Self::find(current, &entry.0) {
+                        return Err(&present.1);
+                    }
+                    current.to_vec()
+                }
+                None => vec![],
+            };
+            bucket.push(entry.clone());
+
+            match self.replace(coords, current, bucket, guard) {
+                Ok(bucket) => return Ok(&bucket.last().unwrap().1),
+                Err(present) => current = present,
+            }
+        }
+    }
+
+    /// Returns the value at `key`, which stays valid for as long as `guard`
+    /// is pinned.
+    #[inline]
+    pub fn get<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> Option<&'g V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        let bucket = unsafe { self.list.get(self.coords(key), guard)? };
+        Self::find(bucket, key).map(|entry| &entry.1)
+    }
+
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.get(key, &pin()).is_some()
+    }
+
+    /// Removes the value at `key`. The returned value stays valid for as long
+    /// as `guard` is pinned, after that it is reclaimed.
+    pub fn remove<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> Option<&'g V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        let coords = self.coords(key);
+        let mut current = unsafe { self.list.get(coords, guard)? };
+        loop {
+            let entry = Self::find(current, key)?;
+
+            // The last entry takes the node with it
+            if current.len() == 1 {
+                let removed = unsafe {
+                    self.list
+                        .remove_if(coords, guard, |present| core::ptr::eq(present, current))
+                };
+                if removed.is_some() {
+                    return Some(&entry.1);
+                }
+                current = unsafe { self.list.get(coords, guard)? };
+                continue;
+            }
+
+            let bucket = current
+                .iter()
+                .filter(|other| !Arc::ptr_eq(other, entry))
+                .cloned()
+                .collect();
+            match self.replace(coords, Some(current), bucket, guard) {
+                Ok(_) => return Some(&entry.1),
+                Err(present) => current = present?,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// Sends every key to one of a handful of buckets.
+    #[derive(Default)]
+    struct Colliding(u64);
+
+    impl Hasher for Colliding {
+        fn finish(&self) -> u64 {
+            self.0 % 3
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            for &b in bytes {
+                self.0 = self.0.wrapping_mul(31).wrapping_add(b as u64);
+            }
+        }
+    }
+
+    type CollidingList = HashedMdList<String, usize, 16, BuildHasherDefault<Colliding>>;
+
+    #[test]
+    fn test_fx_hasher() {
+        // Pinned, so that a change that moves every key is noticed
+        let fx = BuildHasherDefault::<FxHasher>::default();
+        assert_eq!(fx.hash_one(42_u64), 0x5e77_c80c_6b95_bc72);
+        // Two bytes, one byte, and the 0xff that ends a `str`
+        assert_eq!(fx.hash_one("abc"), 0x3206_1e1b_0695_8893);
+    }
+
+    #[test]
+    fn test_hashed() {
+        let guard = &pin();
+        let map = HashedMdList::<String, usize>::new();
+        let mut model = HashMap::new();
+
+        for i in 0..2_000 {
+            let key = format!("user#{:04}", i % 700);
+            map.insert(key.clone(), i);
+            model.insert(key, i);
+            if i % 5 == 0 {
+                let key = format!("user#{:04}", (i * 7) % 700);
+                assert_eq!(map.remove(key.as_str(), guard), model.remove(&key).as_ref());
+            }
+        }
+
+        for i in 0..800 {
+            let key = format!("user#{:04}", i);
+            assert_eq!(map.get(key.as_str(), guard), model.get(&key));
+            assert_eq!(map.contains_key(key.as_str()), model.contains_key(&key));
+        }
+
+        assert_eq!(
+            map.insert_if_absent("user#0001".to_string(), 0, guard),
+            Err(&model["user#0001"])
+        );
+        assert_eq!(map.insert_if_absent("fresh".to_string(), 9, guard), Ok(&9));
+    }
+
+    #[test]
+    fn test_collisions() {
+        let guard = &pin();
+        let map = CollidingList::default();
+        for i in 0..30 {
+            map.insert(i.to_string(), i);
+        }
+        map.insert("7".to_string(), 70);
+
+        for i in (0..30).step_by(2) {
+            assert_eq!(map.remove(i.to_string().as_str(), guard), Some(&i));
+        }
+        for i in 0..30 {
+            let expected = match i {
+                7 => Some(70),
+                _ if i % 2 == 0 => None,
+                _ => Some(i),
+            };
+            assert_eq!(map.get(i.to_string().as_str(), guard), expected.as_ref());
+        }
+    }
+
+    #[test]
+    fn test_parallel_collisions() {
+        let map = CollidingList::default();
+
+        // Threads keep replacing the same three buckets
+        (0..3_000_usize).into_par_iter().for_each(|i| {
+            let guard = &pin();
+            map.insert(i.to_string(), i);
+            if i % 3 == 0 {
+                assert_eq!(map.remove(i.to_string().as_str(), guard), Some(&i));
+            }
+        });
+
+        let guard = &pin();
+        for i in 0..3_000_usize {
+            let expected = if i % 3 == 0 { None } else { Some(&i) };
+            assert_eq!(map.get(i.to_string().as_str(), guard), expected);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! Checks concurrent inserts and removes under every interleaving of their
+//! threads, up to a bound on preemptions, with loom. The atomics of the list and of the
+//! collector are loom's in this build, see `ebr::primitive`. Run them with
+//!
+//! ```text
+//! RUSTFLAGS="--cfg loom" cargo test --release --lib interleavings
+//! ```
+//!
+//! Lists are two dimensional, so that a handful of nodes covers the cases
+//! where inserts meet on the same predecessor or on a node being adopted,
+//! and where they meet a node being unlinked, which takes freezing, helping
+//! and placeholders, see [`List::unlink`].
+//! Every execution gets a collector of its own, since loom tears down its
+//! statics before the thread locals that would unregister from the default
+//! one.
+
+use loom::sync::Arc;
+use loom::thread;
+
+use super::{List, Node};
+use crate::ebr::collector::Collector;
+use crate::ebr::Owned;
+
+type TestList = List<2, Node<2>>;
+type Op = fn(&TestList, &Collector, [u8; 2]);
+
+fn insert(list: &TestList, collector: &Collector, coords: [u8; 2]) {
+    let handle = collector.register();
+    let guard = &handle.pin();
+    unsafe { list.insert(Owned::new(Node::new(coords)).into_shared(guard), guard) }
+}
+
+fn remove(list: &TestList, collector: &Collector, coords: [u8; 2]) {
+    let handle = collector.register();
+    let guard = &handle.pin();
+    let removed = unsafe { list.remove(coords, guard) };
+    assert!(removed.is_some(), "{:?}", coords);
+}
+
+/// Inserts `initial`, then each of `inserts` and removes each of `removes`
+/// on a thread of its own, and checks that the list holds exactly the keys
+/// that are left. Interleavings with more than `preemptions` preemptions
+/// are skipped, unless `LOOM_MAX_PREEMPTIONS` says otherwise.
+fn check(
+    initial: &'static [[u8; 2]],
+    inserts: &'static [[u8; 2]],
+    removes: &'static [[u8; 2]],
+    preemptions: usize,
+) {
+    let mut builder = loom::model::Builder::new();
+    builder.preemption_bound.get_or_insert(preemptions);
+    builder.check(move || {
+        let collector = Collector::new();
+        let list = Arc::new(TestList::new());
+        for &coords in initial {
+            insert(&list, &collector, coords);
+        }
+
+        let ops = inserts
+            .iter()
+            .map(|&coords| (coords, insert as Op))
+            .chain(removes.iter().map(|&coords| (coords, remove as Op)));
+        let threads = ops
+            .map(|(coords, op)| {
+                let (list, collector) = (list.clone(), collector.clone());
+                thread::spawn(move || op(&list, &collector, coords))
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let handle = collector.register();
+        let guard = &handle.pin();
+        let left = initial
+            .iter()
+            .chain(inserts)
+            .filter(|coords| !removes.contains(coords))
+            .copied()
+            .collect::<Vec<_>>();
+        let mut report = list.validate(guard);
+        report.expect_keys(left.iter().copied());
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.live.len(), left.len());
+        for &coords in initial.iter().chain(inserts) {
+            let found = unsafe { list.get(coords, guard) }.is_some();
+            assert_eq!(found, left.contains(&coords), "{:?}", coords);
+        }
+    });
+}
+
+#[test]
+fn interleavings_shared_pred() {
+    // Both hang off [1, 0] in dimension 1, one behind the other
+    check(&[[1, 0]], &[[1, 1], [1, 2]], &[], 2);
+}
+
+#[test]
+fn interleavings_shared_pred_three() {
+    // Pinning and registering with the collector take enough steps that
+    // three threads do not get through even a single preemption in
+    // reasonable time. Without preemptions this runs the inserts one after
+    // the other in every order, the interleavings are up to the tests with
+    // two threads.
+    check(&[[1, 0]], &[[1, 1], [1, 2], [1, 3]], &[], 0);
+}
+
+#[test]
+fn interleavings_adoption() {
+    // [2, 1] takes the place of [2, 2] and adopts its child [3, 0] in
+    // dimension 0, while [4, 0] goes past [3, 0] through the same pointer
+    check(&[[2, 2], [3, 0]], &[[2, 1], [4, 0]], &[], 2);
+}
+
+#[test]
+fn interleavings_adopted_node() {
+    // [2, 3] goes below [2, 2] while [2, 2] moves below [2, 1]
+    check(&[[2, 2], [3, 0]], &[[2, 1], [2, 3]], &[], 2);
+}
+
+#[test]
+fn interleavings_remove_adopted() {
+    // [2, 1] goes in front of [2, 2] and adopts its sibling [3, 0], while
+    // [2, 2] is removed and its child [2, 3] moves to a placeholder
+    check(&[[2, 2], [3, 0], [2, 3]], &[[2, 1]], &[[2, 2]], 2);
+}
+
+#[test]
+fn interleavings_remove_leaf() {
+    // [1, 1] goes below [1, 0] while [1, 0] is spliced out
+    check(&[[1, 0], [2, 0]], &[[1, 1]], &[[1, 0]], 2);
+}
+
+#[test]
+fn interleavings_remove_both() {
+    // The placeholder for [1, 0] loses its only child [1, 1]
+    check(&[[1, 0], [1, 1], [2, 0]], &[], &[[1, 0], [1, 1]], 2);
+}
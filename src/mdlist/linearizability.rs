@@ -0,0 +1,314 @@
+//! Records concurrent operations on an [`MdList`] and checks that the
+//! history is linearizable with respect to a sequential map.
+//!
+//! Every operation takes a tick of a shared clock when it is invoked and
+//! another one when it returns. One operation precedes another if it
+//! returned before the other was invoked, otherwise the two overlap and may
+//! take effect in either order. The history is linearizable if there is a
+//! sequential order of all operations that respects precedence and in which
+//! every operation returns what it would on a sequential map.
+//!
+//! Operations on different keys commute, so the history is checked one key
+//! at a time. Each key is searched the way of Wing and Gong: operations are
+//! linearized in every order that respects precedence, until one matches the
+//! sequential map. Following Lowe, the search goes through the history in
+//! time, linearizes operations just in time, and skips configurations that
+//! it has explored before, see `linearize`. A search that fails has to
+//! exhaust every order, which this keeps to the operations that overlap.
+
+use core::fmt::Debug;
+use core::hash::Hash;
+use core::sync::atomic::{AtomicU64, Ordering::SeqCst};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use super::{MdList, ToCoords};
+use crate::ebr::pin;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Call<V> {
+    Insert(V),
+    Get,
+    Remove,
+}
+
+/// An operation on `key`, which returned `ret` and took effect somewhere
+/// between `invoked` and `returned`.
+#[derive(Debug, Clone)]
+pub(super) struct Op<K, V> {
+    pub key: K,
+    pub call: Call<V>,
+    pub ret: Option<V>,
+    pub invoked: u64,
+    pub returned: u64,
+}
+
+/// The operations of all threads on one map.
+pub(super) struct History<K, V> {
+    clock: AtomicU64,
+    ops: Mutex<Vec<Op<K, V>>>,
+}
+
+/// The operations of a single thread, which join the history when dropped.
+pub(super) struct Log<'h, K, V> {
+    history: &'h History<K, V>,
+    ops: Vec<Op<K, V>>,
+}
+
+impl<K, V> History<K, V> {
+    pub fn new() -> Self {
+        Self {
+            clock: AtomicU64::new(0),
+            ops: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn log(&self) -> Log<'_, K, V> {
+        Log {
+            history: self,
+            ops: vec![],
+        }
+    }
+
+    pub fn into_ops(self) -> Vec<Op<K, V>> {
+        self.ops.into_inner().unwrap()
+    }
+}
+
+impl<K: Clone, V: Clone> Log<'_, K, V> {
+    fn record(&mut self, key: &K, call: Call<V>, f: impl FnOnce() -> Option<V>) {
+        let invoked = self.history.clock.fetch_add(1, SeqCst);
+        let ret = f();
+        let returned = self.history.clock.fetch_add(1, SeqCst);
+        self.ops.push(Op {
+            key: key.clone(),
+            call,
+            ret,
+            invoked,
+            returned,
+        });
+    }
+
+    pub fn insert<const DIM: usize>(&mut self, map: &MdList<K, V, DIM>, key: K, value: V)
+    where
+        K: ToCoords<DIM>,
+    {
+        let call = Call::Insert(value.clone());
+        self.record(&key.clone(), call, || {
+            map.insert(key, value);
+            None
+        });
+    }
+
+    pub fn get<const DIM: usize>(&mut self, map: &MdList<K, V, DIM>, key: K)
+    where
+        K: ToCoords<DIM>,
+    {
+        self.record(&key.clone(), Call::Get, || map.get(key, &pin()).cloned());
+    }
+
+    pub fn remove<const DIM: usize>(&mut self, map: &MdList<K, V, DIM>, key: K)
+    where
+        K: ToCoords<DIM>,
+    {
+        self.record(&key.clone(), Call::Remove, || {
+            map.remove(key, &pin()).cloned()
+        });
+    }
+}
+
+impl<K, V> Drop for Log<'_, K, V> {
+    fn drop(&mut self) {
+        let ops = core::mem::take(&mut self.ops);
+        self.history.ops.lock().unwrap().extend(ops);
+    }
+}
+
+/// Applies `op` to the value of its key in a sequential map, returning the
+/// new value, or `None` if the map would have returned something else.
+fn apply<K, V: Clone + PartialEq>(value: &Option<V>, op: &Op<K, V>) -> Option<Option<V>> {
+    match &op.call {
+        Call::Insert(new) => Some(Some(new.clone())),
+        Call::Get if op.ret == *value => Some(value.clone()),
+        Call::Remove if op.ret == *value => Some(None),
+        _ => None,
+    }
+}
+
+/// Operations on a key that cannot be linearized, since the last point where
+/// nothing was pending, up to the one that fails.
+#[derive(Debug)]
+pub(super) struct NonLinearizable<K, V> {
+    /// The values the key may have had at that point
+    pub before: Vec<Option<V>>,
+    pub ops: Vec<Op<K, V>>,
+}
+
+/// A point of a search through the history of a key: the value, and the
+/// pending operations that have already taken effect.
+type Config<V> = (Option<V>, Vec<usize>);
+
+/// Checks the operations on a single key, which start from an absent key.
+///
+/// Operations are linearized just in time: the search steps through the
+/// invocations and returns in order, and only when an operation returns do
+/// we linearize it, after any of the pending operations that may come first.
+/// So the configurations are bounded by the operations that are pending at
+/// the same time, not by the length of the history.
+fn linearize<K: Clone, V>(ops: Vec<Op<K, V>>) -> Result<(), NonLinearizable<K, V>>
+where
+    V: Clone + Eq + Hash,
+{
+    let mut events = ops
+        .iter()
+        .enumerate()
+        .flat_map(|(i, op)| [(op.invoked, i), (op.returned, i)])
+        .collect::<Vec<_>>();
+    events.sort_unstable();
+
+    let mut configs: HashSet<Config<V>> = HashSet::from([(None, vec![])]);
+    let mut pending = vec![];
+    let mut since = vec![];
+    let mut before = vec![None];
+    for (time, i) in events {
+        if time == ops[i].invoked {
+            pending.push(i);
+            since.push(i);
+            continue;
+        }
+
+        let mut next = HashSet::new();
+        let mut seen = HashSet::new();
+        for (value, done) in configs {
+            if let Some(pos) = done.iter().position(|&j| j == i) {
+                let mut done = done;
+                done.remove(pos);
+                next.insert((value, done));
+            } else {
+                search(&ops, &pending, i, value, done, &mut seen, &mut next);
+            }
+        }
+        if next.is_empty() {
+            return Err(NonLinearizable {
+                before,
+                ops: since.iter().map(|&j| ops[j].clone()).collect(),
+            });
+        }
+
+        configs = next;
+        pending.retain(|&j| j != i);
+        if pending.is_empty() {
+            since.clear();
+            before = configs.iter().map(|(value, _)| value.clone()).collect();
+        }
+    }
+    Ok(())
+}
+
+/// Linearizes any of the `pending` operations that are not `done` yet after
+/// `value`, in every order, up to `target`. Collects the configurations
+/// right after `target` took effect in `next`. Configurations that are in
+/// `seen` have been explored before.
+fn search<K, V>(
+    ops: &[Op<K, V>],
+    pending: &[usize],
+    target: usize,
+    value: Option<V>,
+    done: Vec<usize>,
+    seen: &mut HashSet<Config<V>>,
+    next: &mut HashSet<Config<V>>,
+) where
+    V: Clone + Eq + Hash,
+{
+    for &j in pending {
+        if done.contains(&j) {
+            continue;
+        }
+        let Some(after) = apply(&value, &ops[j]) else {
+            continue;
+        };
+
+        if j == target {
+            next.insert((after, done.clone()));
+            continue;
+        }
+        let mut done = done.clone();
+        done.push(j);
+        done.sort_unstable();
+        if seen.insert((after.clone(), done.clone())) {
+            search(ops, pending, target, after, done, seen, next);
+        }
+    }
+}
+
+/// Checks that `ops` are linearizable.
+pub(super) fn check<K, V>(ops: Vec<Op<K, V>>) -> Result<(), NonLinearizable<K, V>>
+where
+    K: Clone + Hash + Eq,
+    V: Clone + Eq + Hash,
+{
+    let mut by_key: HashMap<K, Vec<Op<K, V>>> = HashMap::new();
+    for op in ops {
+        by_key.entry(op.key.clone()).or_default().push(op);
+    }
+    by_key.into_values().try_for_each(linearize)
+}
+
+/// Panics with the offending operations if `history` is not linearizable.
+pub(super) fn assert_linearizable<K, V>(history: History<K, V>)
+where
+    K: Clone + Hash + Eq + Debug,
+    V: Clone + Eq + Hash + Debug,
+{
+    if let Err(failure) = check(history.into_ops()) {
+        panic!("history is not linearizable: {:#?}", failure);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn op(call: Call<u32>, ret: Option<u32>, invoked: u64, returned: u64) -> Op<u8, u32> {
+        Op {
+            key: 0,
+            call,
+            ret,
+            invoked,
+            returned,
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        // A read that overlaps an insert may see it or not
+        let ops = vec![
+            op(Call::Insert(1), None, 0, 5),
+            op(Call::Get, None, 1, 2),
+            op(Call::Get, Some(1), 3, 4),
+        ];
+        assert!(check(ops).is_ok());
+
+        // But not after the insert returned
+        let ops = vec![op(Call::Insert(1), None, 0, 1), op(Call::Get, None, 2, 3)];
+        let failure = check(ops).unwrap_err();
+        assert_eq!(failure.before, vec![Some(1)]);
+        assert_eq!(failure.ops.len(), 1);
+
+        // Only one of two overlapping removes gets the value
+        let ops = vec![
+            op(Call::Insert(1), None, 0, 1),
+            op(Call::Remove, Some(1), 2, 5),
+            op(Call::Remove, Some(1), 3, 4),
+        ];
+        assert!(check(ops).is_err());
+
+        // A read sees the last of two inserts
+        let ops = vec![
+            op(Call::Insert(1), None, 0, 1),
+            op(Call::Insert(2), None, 2, 3),
+            op(Call::Get, Some(1), 4, 5),
+        ];
+        assert!(check(ops).is_err());
+    }
+}
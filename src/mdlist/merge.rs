@@ -0,0 +1,126 @@
+//! Merging of two iterators that are sorted by key.
+
+/// Merges two sorted iterators over `(key, value)` pairs into one, from both
+/// ends. On equal keys the entry from `left` is returned first.
+///
+/// An item that was taken from one end but not returned yet stays buffered,
+/// so the other end can still pick it up once the inner iterator has run dry.
+pub struct Merge<I: Iterator, J> {
+    left: I,
+    right: J,
+    left_front: Option<I::Item>,
+    right_front: Option<I::Item>,
+    left_back: Option<I::Item>,
+    right_back: Option<I::Item>,
+}
+
+impl<I: Iterator, J> Merge<I, J> {
+    pub fn new(left: I, right: J) -> Self {
+        Self {
+            left,
+            right,
+            left_front: None,
+            right_front: None,
+            left_back: None,
+            right_back: None,
+        }
+    }
+}
+
+impl<K: Ord, V, I, J> Iterator for Merge<I, J>
+where
+    I: DoubleEndedIterator<Item = (K, V)>,
+    J: DoubleEndedIterator<Item = (K, V)>,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let left = self
+            .left_front
+            .take()
+            .or_else(|| self.left.next())
+            .or_else(|| self.left_back.take());
+        let right = self
+            .right_front
+            .take()
+            .or_else(|| self.right.next())
+            .or_else(|| self.right_back.take());
+
+        match (left, right) {
+            (Some(left), Some(right)) if right.0 < left.0 => {
+                self.left_front = Some(left);
+                Some(right)
+            }
+            (Some(left), right) => {
+                self.right_front = right;
+                Some(left)
+            }
+            (None, right) => right,
+        }
+    }
+}
+
+impl<K: Ord, V, I, J> DoubleEndedIterator for Merge<I, J>
+where
+    I: DoubleEndedIterator<Item = (K, V)>,
+    J: DoubleEndedIterator<Item = (K, V)>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let left = self
+            .left_back
+            .take()
+            .or_else(|| self.left.next_back())
+            .or_else(|| self.left_front.take());
+        let right = self
+            .right_back
+            .take()
+            .or_else(|| self.right.next_back())
+            .or_else(|| self.right_front.take());
+
+        match (left, right) {
+            (Some(left), Some(right)) if left.0 > right.0 => {
+                self.right_back = Some(right);
+                Some(left)
+            }
+            (left, Some(right)) => {
+                self.left_back = left;
+                Some(right)
+            }
+            (left, None) => left,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(keys: &[u32]) -> Vec<(u32, ())> {
+        keys.iter().map(|&k| (k, ())).collect()
+    }
+
+    #[test]
+    fn test_merge() {
+        let left = pairs(&[1, 4, 5, 9]);
+        let right = pairs(&[2, 3, 6, 10, 11]);
+        let merged = || Merge::new(left.clone().into_iter(), right.clone().into_iter());
+
+        let forward: Vec<u32> = merged().map(|(k, _)| k).collect();
+        assert_eq!(forward, [1, 2, 3, 4, 5, 6, 9, 10, 11]);
+
+        let backward: Vec<u32> = merged().rev().map(|(k, _)| k).collect();
+        assert_eq!(backward, [11, 10, 9, 6, 5, 4, 3, 2, 1]);
+
+        // Alternate between both ends, every key is returned exactly once
+        let mut iter = merged();
+        let mut keys = Vec::new();
+        loop {
+            match (iter.next(), iter.next_back()) {
+                (None, None) => break,
+                (front, back) => keys.extend(front.into_iter().chain(back).map(|(k, _)| k)),
+            }
+        }
+        keys.sort_unstable();
+        assert_eq!(keys, forward);
+    }
+}
@@ -0,0 +1,2495 @@
+use core::marker::PhantomData;
+use core::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed, Release, SeqCst};
+use std::collections::VecDeque;
+use std::ops::{Bound, Deref, RangeBounds};
+
+use crate::ebr::{pin, unprotected, Atomic, Guard, Owned, Shared};
+
+#[cfg(test)]
+mod bench;
+mod bulk;
+mod children;
+mod composite;
+mod coords;
+mod cursor;
+mod entry;
+mod hashed;
+#[cfg(all(test, loom))]
+mod interleavings;
+#[cfg(test)]
+mod linearizability;
+mod merge;
+#[cfg(test)]
+mod model;
+mod par;
+mod partition;
+mod query;
+mod queue;
+#[cfg(feature = "slab")]
+mod slab;
+mod validate;
+
+pub use composite::{CoordsBuilder, CoordsReader, FromField, ToField};
+pub use coords::{FromCoords, Radix, ToCoords};
+pub use cursor::Cursor;
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use hashed::HashedMdList;
+use merge::Merge;
+pub use partition::Partition;
+pub use query::{BoxIter, Metric};
+pub use queue::MdPriorityQueue;
+pub use validate::{Report, Violation};
+
+use children::Children;
+
+#[derive(Debug)]
+pub struct AdoptDesc<const DIM: usize> {
+    dp: usize,
+    dc: usize,
+    // Not owned, the node is reclaimed by whoever unlinks it
+    curr: Atomic<Node<DIM>>,
+}
+
+#[derive(Debug)]
+pub struct Pred<'g, const DIM: usize> {
+    pred: Shared<'g, Node<DIM>>,
+    curr: Shared<'g, Node<DIM>>,
+    dp: usize,
+    dc: usize,
+}
+
+#[derive(Debug)]
+pub struct Node<const DIM: usize> {
+    adesc: Atomic<AdoptDesc<DIM>>,
+    children: Children<DIM>,
+    coords: [u8; DIM],
+}
+
+impl<const DIM: usize> IsElement<DIM, Node<DIM>> for Node<DIM> {
+    fn entry_of(entry: &Node<DIM>) -> &Node<DIM> {
+        entry
+    }
+
+    unsafe fn element_of(entry: &Node<DIM>) -> &Node<DIM> {
+        entry
+    }
+
+    fn alloc<'g>(element: Node<DIM>, guard: &'g Guard) -> Element<'g, Node<DIM>, Self> {
+        Owned::new(element).into_shared(guard).into()
+    }
+
+    unsafe fn dealloc(element: Element<'_, Node<DIM>, Self>) {
+        drop(element.shared.into_owned());
+    }
+
+    unsafe fn finalize(entry: &Node<DIM>, guard: &Guard) {
+        guard.defer_destroy(Shared::from(Self::element_of(entry) as *const _));
+    }
+
+    unsafe fn take(entry: &Node<DIM>) -> Node<DIM> {
+        *Owned::from_raw(entry as *const _ as *mut Self).into_box()
+    }
+}
+
+pub struct NodeWithValue<const DIM: usize, V> {
+    node: Node<DIM>,
+    value: V,
+}
+
+impl<const DIM: usize, V> std::ops::Deref for NodeWithValue<DIM, V> {
+    type Target = V;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<const DIM: usize, V> NodeWithValue<DIM, V> {
+    #[inline]
+    pub fn new(coords: [u8; DIM], value: V) -> Self {
+        Self {
+            node: Node::new(coords),
+            value,
+        }
+    }
+}
+
+impl<const DIM: usize, V> NodeWithValue<DIM, V> {
+    fn entry_offset() -> usize {
+        use std::mem::MaybeUninit;
+        let local: MaybeUninit<Self> = MaybeUninit::uninit();
+
+        // MaybeUninit is repr(transparent so we can treat a pointer as a pointer to the inner value)
+        let local_ref: &Self = unsafe { &*(&local as *const MaybeUninit<Self> as *const Self) };
+        let entry_ref: &Node<DIM> = &local_ref.node;
+
+        let local_ptr = local_ref as *const Self;
+        let entry_ptr = entry_ref as *const Node<DIM>;
+
+        entry_ptr as usize - local_ptr as usize
+    }
+}
+
+impl<const DIM: usize, V> IsElement<DIM, NodeWithValue<DIM, V>> for NodeWithValue<DIM, V> {
+    fn entry_of(entry: &NodeWithValue<DIM, V>) -> &Node<DIM> {
+        &entry.node
+    }
+
+    unsafe fn element_of(entry: &Node<DIM>) -> &NodeWithValue<DIM, V> {
+        let ptr = (entry as *const Node<DIM> as usize - Self::entry_offset()) as *const Self;
+        &*ptr
+    }
+
+    fn alloc<'g>(element: Self, guard: &'g Guard) -> Element<'g, Self, Self> {
+        Owned::new(element).into_shared(guard).into()
+    }
+
+    unsafe fn dealloc(element: Element<'_, Self, Self>) {
+        drop(element.shared.into_owned());
+    }
+
+    unsafe fn finalize(entry: &Node<DIM>, guard: &Guard) {
+        guard.defer_destroy(Shared::from(Self::element_of(entry) as *const _));
+    }
+
+    unsafe fn take(entry: &Node<DIM>) -> Self {
+        *Owned::from_raw(Self::element_of(entry) as *const _ as *mut Self).into_box()
+    }
+}
+
+/// Allocates the nodes of maps and queues, from the slab if the `slab`
+/// feature is enabled.
+#[cfg(not(feature = "slab"))]
+type Alloc<const DIM: usize, V> = NodeWithValue<DIM, V>;
+#[cfg(feature = "slab")]
+type Alloc<const DIM: usize, V> = slab::Slab<V>;
+
+/// The list of a map or queue with values of type `V`
+type Values<const DIM: usize, V> = List<DIM, NodeWithValue<DIM, V>, Alloc<DIM, V>>;
+
+/// An element that `C` allocated, which is the only kind a list of `C`
+/// takes, since `C` reclaims it once it is removed. An element that is its
+/// own [`IsElement`] is allocated with [`Owned`], so any pointer to one will
+/// do.
+pub struct Element<'g, T, C: ?Sized> {
+    shared: Shared<'g, T>,
+    _alloc: PhantomData<C>,
+}
+
+impl<'g, T, C: ?Sized> Element<'g, T, C> {
+    /// Dereferences the element, see [`Shared::deref`].
+    pub(crate) unsafe fn deref(&self) -> &'g T {
+        self.shared.deref()
+    }
+}
+
+impl<T, C: ?Sized> Clone for Element<'_, T, C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, C: ?Sized> Copy for Element<'_, T, C> {}
+
+impl<'g, T> From<Shared<'g, T>> for Element<'g, T, T> {
+    fn from(shared: Shared<'g, T>) -> Self {
+        Self {
+            shared,
+            _alloc: PhantomData,
+        }
+    }
+}
+
+pub trait IsElement<const DIM: usize, T> {
+    fn entry_of(_: &T) -> &Node<DIM>;
+    unsafe fn element_of(_: &Node<DIM>) -> &T;
+    /// Moves `element` to the heap. Once it is linked into a list it is
+    /// reclaimed through `finalize`, until then through `dealloc`.
+    fn alloc<'g>(element: T, guard: &'g Guard) -> Element<'g, T, Self>;
+    unsafe fn dealloc(_: Element<'_, T, Self>);
+    unsafe fn finalize(_: &Node<DIM>, _: &Guard);
+    /// Moves the element of a node that nobody else can reach anymore out,
+    /// and frees the node right away.
+    unsafe fn take(_: &Node<DIM>) -> T;
+}
+
+/// Iterates over the nodes of a [`List`] within a range of coordinates.
+///
+/// Nodes are returned in lexicographic order of their coordinates, both from
+/// the front and from the back. This follows from the layout of the list: a
+/// node reached through dimension `d` agrees with its children on the
+/// coordinates before `d`, and the child in dimension `i` is larger than the
+/// node in dimension `i`. So a node comes before all of its children, the
+/// child in the last dimension comes before the child in the one before it,
+/// and so on until the child in dimension `d`, which is the next sibling.
+///
+/// The iterator is weakly consistent: nodes that are inserted or removed
+/// concurrently may or may not be returned.
+pub struct Iter<'g, const DIM: usize, T, C: IsElement<DIM, T>> {
+    guard: &'g Guard,
+    root: Shared<'g, Node<DIM>>,
+    lo: Bound<[u8; DIM]>,
+    hi: Bound<[u8; DIM]>,
+    // Nodes to expand from the front, along with the dimension we reached
+    // them through. Seeded on the first call to `next`.
+    front: Option<VecDeque<(Shared<'g, Node<DIM>>, usize)>>,
+    // Seeded on the first call to `next_back`
+    back: Option<VecDeque<Visit<'g, DIM>>>,
+    // Where the two ends are, so that they stop when they meet
+    last_front: Option<[u8; DIM]>,
+    last_back: Option<[u8; DIM]>,
+    _marker: PhantomData<(&'g T, C)>,
+}
+
+/// A step of a traversal in descending order, where a node is visited after
+/// all of its children.
+enum Visit<'g, const DIM: usize> {
+    Expand(Shared<'g, Node<DIM>>, usize),
+    Yield(Shared<'g, Node<DIM>>),
+}
+
+fn is_above<const DIM: usize>(coords: &[u8; DIM], hi: &Bound<[u8; DIM]>) -> bool {
+    match hi {
+        Bound::Unbounded => false,
+        Bound::Included(hi) => coords > hi,
+        Bound::Excluded(hi) => coords >= hi,
+    }
+}
+
+fn is_below<const DIM: usize>(coords: &[u8; DIM], lo: &Bound<[u8; DIM]>) -> bool {
+    match lo {
+        Bound::Unbounded => false,
+        Bound::Included(lo) => coords < lo,
+        Bound::Excluded(lo) => coords <= lo,
+    }
+}
+
+impl<'g, const DIM: usize, T: 'g, C: IsElement<DIM, T>> Iter<'g, DIM, T, C> {
+    /// Finds the nodes to expand to visit everything past the lower bound in
+    /// ascending order.
+    ///
+    /// This follows the same path as `List::locate_pred`. On the way down we
+    /// push the children of every passed node that lie entirely past `lo`.
+    /// These are all larger than whatever we find further down the path, so
+    /// they end up below it on the stack.
+    unsafe fn seek_front(&self) -> VecDeque<(Shared<'g, Node<DIM>>, usize)> {
+        let mut stack = VecDeque::new();
+        let (mut node, mut dim) = (self.root, 0);
+        let (lo, inclusive) = match self.lo {
+            Bound::Unbounded => {
+                if !node.is_null() {
+                    stack.push_front((node, dim));
+                }
+                return stack;
+            }
+            Bound::Included(lo) => (lo, true),
+            Bound::Excluded(lo) => (lo, false),
+        };
+
+        while !node.is_null() {
+            let coords = &node.deref().coords;
+
+            // Every node we pass agrees with `lo` up to `dim`
+            let k = (dim..DIM).find(|&d| coords[d] != lo[d]).unwrap_or(DIM);
+            if (k < DIM && coords[k] > lo[k]) || (k == DIM && inclusive) {
+                stack.push_front((node, dim));
+                break;
+            }
+
+            for d in dim..k {
+                let child = node.deref().children.load(d, Acquire, self.guard);
+                if !child.is_null() {
+                    stack.push_front((child, d));
+                }
+            }
+
+            if k == DIM {
+                break;
+            }
+
+            node = node.deref().children.load(k, Acquire, self.guard);
+            dim = k;
+        }
+
+        stack
+    }
+
+    /// Finds the nodes to visit everything before the upper bound in
+    /// descending order. This mirrors `seek_front`.
+    unsafe fn seek_back(&self) -> VecDeque<Visit<'g, DIM>> {
+        let mut stack = VecDeque::new();
+        let (mut node, mut dim) = (self.root, 0);
+        let (hi, inclusive) = match self.hi {
+            Bound::Unbounded => {
+                if !node.is_null() {
+                    stack.push_front(Visit::Expand(node, dim));
+                }
+                return stack;
+            }
+            Bound::Included(hi) => (hi, true),
+            Bound::Excluded(hi) => (hi, false),
+        };
+
+        while !node.is_null() {
+            let coords = &node.deref().coords;
+
+            // The node, its children and its siblings all lie past `hi`
+            let k = (dim..DIM).find(|&d| coords[d] != hi[d]).unwrap_or(DIM);
+            if (k < DIM && coords[k] > hi[k]) || (k == DIM && !inclusive) {
+                break;
+            }
+
+            stack.push_front(Visit::Yield(node));
+            if k == DIM {
+                break;
+            }
+
+            for d in (k + 1..DIM).rev() {
+                let child = node.deref().children.load(d, Acquire, self.guard);
+                if !child.is_null() {
+                    stack.push_front(Visit::Expand(child, d));
+                }
+            }
+
+            node = node.deref().children.load(k, Acquire, self.guard);
+            dim = k;
+        }
+
+        stack
+    }
+}
+
+impl<'g, const DIM: usize, T: 'g, C: IsElement<DIM, T>> Iterator for Iter<'g, DIM, T, C> {
+    type Item = &'g T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        unsafe {
+            if self.front.is_none() {
+                self.front = Some(self.seek_front());
+            }
+
+            let front = self.front.as_mut().unwrap();
+            while let Some((node, dim)) = front.pop_front() {
+                let coords = &node.deref().coords;
+
+                // Everything that is left lies past this node
+                if is_above(coords, &self.hi) || self.last_back.is_some_and(|b| *coords >= b) {
+                    front.clear();
+                    return None;
+                }
+
+                // Dimensions below `dim` are adopted or unused, and may point
+                // to nodes that have since been reclaimed
+                for d in dim..DIM {
+                    let child = node.deref().children.load(d, Acquire, self.guard);
+                    if !child.is_null() {
+                        front.push_front((child, d));
+                    }
+                }
+
+                // Deleted nodes may still route to live children
+                if node.tag() & 0x2 == 0 {
+                    self.last_front = Some(*coords);
+                    return Some(C::element_of(node.deref()));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl<'g, const DIM: usize, T: 'g, C: IsElement<DIM, T>> DoubleEndedIterator
+    for Iter<'g, DIM, T, C>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        unsafe {
+            if self.back.is_none() {
+                self.back = Some(self.seek_back());
+            }
+
+            let back = self.back.as_mut().unwrap();
+            while let Some(visit) = back.pop_front() {
+                match visit {
+                    Visit::Expand(node, dim) => {
+                        back.push_front(Visit::Yield(node));
+                        for d in (dim..DIM).rev() {
+                            let child = node.deref().children.load(d, Acquire, self.guard);
+                            if !child.is_null() {
+                                back.push_front(Visit::Expand(child, d));
+                            }
+                        }
+                    }
+                    Visit::Yield(node) => {
+                        let coords = &node.deref().coords;
+                        if is_below(coords, &self.lo)
+                            || self.last_front.is_some_and(|f| *coords <= f)
+                        {
+                            back.clear();
+                            return None;
+                        }
+
+                        if node.tag() & 0x2 == 0 {
+                            self.last_back = Some(*coords);
+                            return Some(C::element_of(node.deref()));
+                        }
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl<const DIM: usize> Default for Node<DIM> {
+    fn default() -> Self {
+        Self {
+            children: Children::new(),
+            adesc: Atomic::null(),
+            coords: [0; DIM],
+        }
+    }
+}
+
+impl<const DIM: usize> Node<DIM> {
+    /// Set on the descriptor pointer of a placeholder, see [`List::unlink`]
+    const PLACEHOLDER: usize = 1;
+
+    #[inline]
+    fn new(coords: [u8; DIM]) -> Self {
+        Self {
+            children: Children::new(),
+            adesc: Atomic::null(),
+            coords,
+        }
+    }
+
+    /// Returns `true` if the node has no element and only routes to the
+    /// children of a removed node.
+    fn is_placeholder(&self, guard: &Guard) -> bool {
+        self.adesc.load(Relaxed, guard).tag() & Self::PLACEHOLDER != 0
+    }
+}
+
+pub struct List<const DIM: usize, T, C: IsElement<DIM, T> = T> {
+    head: Atomic<Node<DIM>>,
+    _marker: PhantomData<(T, C)>,
+}
+
+// The tags of child pointers take the three lowest bits
+const _: () = assert!(core::mem::align_of::<Node<0>>() >= 8);
+
+impl<const DIM: usize, T, C: IsElement<DIM, T>> List<DIM, T, C> {
+    const UNMARKED: usize = 0;
+    const ADP: usize = 1;
+    const DEL: usize = 2;
+    /// Set on the child pointers of a deleted node while it is being
+    /// unlinked
+    const FRZ: usize = 4;
+    const ALL: usize = Self::ADP | Self::DEL | Self::FRZ;
+
+    pub fn new() -> Self {
+        Self {
+            head: Atomic::new(Node::default()),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Completes the adoption that `node` was inserted with: marks the child
+    /// pointers of the node it was inserted before with `ADP` and copies
+    /// them to `node`.
+    ///
+    /// The adopted node finishes its own adoption first, since the pointers
+    /// it is still to copy would otherwise be marked before they arrive.
+    pub(crate) unsafe fn finish_inserting<'g>(
+        &'g self,
+        node: Shared<'g, Node<DIM>>,
+        adesc: Shared<'g, AdoptDesc<DIM>>,
+        guard: &'g Guard,
+    ) {
+        let ad = adesc.deref();
+        let curr = ad.curr.load(Relaxed, guard);
+        let cad = curr.deref().adesc.load(Acquire, guard);
+        if !cad.is_null() {
+            self.finish_inserting(curr, cad, guard);
+        }
+
+        for i in ad.dp..ad.dc {
+            let slot = curr.deref().children.slot(i, guard);
+            let mut child = slot.load(Relaxed, guard);
+            while slot
+                .compare_and_set_weak(
+                    child,
+                    child.with_tag(child.tag() | Self::ADP),
+                    Release,
+                    guard,
+                )
+                .is_err()
+            {
+                child = slot.load(Relaxed, guard);
+            }
+
+            // There is nothing to copy from an empty dimension, and writing
+            // null would only allocate the pointers of `node` past its inline
+            // ones. A pointer of `node` that an unlink emptied keeps the
+            // `DEL` mark, so a late helper does not copy the unlinked node
+            // back. Adopting from a frozen node is fine, its pointers no
+            // longer change, but the copies are not frozen.
+            if !child.is_null() && node.deref().children.load(i, Relaxed, guard).is_null() {
+                let _ = node.deref().children.slot(i, guard).compare_and_set_weak(
+                    Shared::null(),
+                    child.with_tag(child.tag() & Self::DEL),
+                    Release,
+                    guard,
+                );
+            }
+        }
+
+        // Whoever clears the descriptor is responsible for reclaiming it
+        if node
+            .deref()
+            .adesc
+            .compare_and_set(adesc, Shared::null(), AcqRel, guard)
+            .is_ok()
+        {
+            guard.defer_destroy(adesc);
+        }
+    }
+
+    pub(crate) unsafe fn locate_pred<'g>(
+        &'g self,
+        coords: [u8; DIM],
+        guard: &'g Guard,
+    ) -> Pred<'g, DIM> {
+        'retry: loop {
+            // The head sorts before every key, so we start at its child
+            let (mut dp, mut dc) = (0, 0);
+            let mut parent = self.head.load(Relaxed, guard);
+            let mut curr = parent.deref().children.load(0, Acquire, guard);
+            while dc < DIM {
+                while !curr.is_null() && coords[dc] > curr.deref().coords[dc] {
+                    dp = dc;
+                    parent = curr;
+                    let ad = curr.deref().adesc.load(Relaxed, guard);
+                    if !ad.is_null() && dp >= ad.deref().dp && dp <= ad.deref().dc {
+                        self.finish_inserting(curr, ad, guard);
+                    }
+                    // Keep the tag, it tells us if `curr` is deleted
+                    curr = curr.deref().children.load(dc, Acquire, guard);
+
+                    // No CAS on the pointers of a node that is being unlinked
+                    // succeeds, so we finish the unlink and start over
+                    if curr.tag() & Self::FRZ != 0 {
+                        self.unlink(parent.with_tag(Self::UNMARKED), guard);
+                        continue 'retry;
+                    }
+                }
+
+                if curr.is_null() || coords[dc] < curr.deref().coords[dc] {
+                    break;
+                } else {
+                    dc += 1;
+                }
+            }
+
+            return Pred {
+                pred: parent,
+                curr,
+                dp,
+                dc,
+            };
+        }
+    }
+
+    pub(crate) unsafe fn get<'g>(&'g self, coords: [u8; DIM], guard: &'g Guard) -> Option<&'g T> {
+        Self::present(&self.locate_pred(coords, guard))
+    }
+
+    /// Returns the element that `locate_pred` found at the coordinates it
+    /// was given, unless it is absent or deleted.
+    pub(crate) unsafe fn present<'g>(p: &Pred<'g, DIM>) -> Option<&'g T> {
+        if p.dc == DIM && (p.curr.tag() & Self::DEL == 0) {
+            return Some(C::element_of(p.curr.deref()));
+        }
+        None
+    }
+
+    /// Returns the first node in lexicographic order that is not deleted.
+    ///
+    /// The deleted nodes before it, placeholders or nodes whose unlinks are
+    /// still in progress, are walked in order like [`Iter`] does.
+    pub(crate) unsafe fn first<'g>(&'g self, guard: &'g Guard) -> Option<&'g T> {
+        let head = self.head.load(Relaxed, guard);
+        let root = head.deref().children.load(0, Acquire, guard);
+        if root.is_null() {
+            return None;
+        }
+
+        // Deleted nodes from the root down, along with the dimension we
+        // reached them through and the last child dimension we followed
+        let mut path = vec![(root, 0, DIM)];
+        while let Some(&(node, dim, next)) = path.last() {
+            if node.tag() & Self::DEL == 0 {
+                return Some(C::element_of(node.deref()));
+            }
+
+            if next > dim {
+                path.last_mut().unwrap().2 = next - 1;
+                let child = node.deref().children.load(next - 1, Acquire, guard);
+                if !child.is_null() {
+                    path.push((child, next - 1, DIM));
+                }
+                continue;
+            }
+
+            path.pop();
+        }
+
+        None
+    }
+
+    /// Removes the node at `coords`, returning it if it was present.
+    ///
+    /// The node is first logically deleted by marking the pointer leading to
+    /// it with `DEL`, which is the linearization point. Afterwards we
+    /// physically unlink it, see [`List::unlink`].
+    pub(crate) unsafe fn remove<'g>(
+        &'g self,
+        coords: [u8; DIM],
+        guard: &'g Guard,
+    ) -> Option<&'g T> {
+        self.remove_if(coords, guard, |_| true)
+    }
+
+    /// Removes the node at `coords` if `pred` holds for it. `pred` is called
+    /// again whenever the node changes before it could be marked.
+    pub(crate) unsafe fn remove_if<'g, F>(
+        &'g self,
+        coords: [u8; DIM],
+        guard: &'g Guard,
+        mut pred: F,
+    ) -> Option<&'g T>
+    where
+        F: FnMut(&'g T) -> bool,
+    {
+        loop {
+            let p = self.locate_pred(coords, guard);
+            if p.dc != DIM || (p.curr.tag() & Self::DEL) != 0 {
+                return None;
+            }
+            if !pred(C::element_of(p.curr.deref())) {
+                return None;
+            }
+
+            // Fails if the node was replaced, deleted, or if the dimension of
+            // the predecessor has been adopted, in which case we try again
+            let curr = p.curr.with_tag(Self::UNMARKED);
+            if p.pred
+                .deref()
+                .children
+                .slot(p.dp, guard)
+                .compare_and_set(curr, curr.with_tag(Self::DEL), AcqRel, guard)
+                .is_ok()
+            {
+                self.unlink(curr, guard);
+                return Some(C::element_of(curr.deref()));
+            }
+        }
+    }
+
+    /// Physically unlinks the deleted `node`, which any thread may help
+    /// with.
+    ///
+    /// The child pointers of `node` from the dimension `dp` it is reached
+    /// through on are frozen with `FRZ` first, after its own adoption is
+    /// done. No insert or unlink can change them from then on, and whoever
+    /// runs into one helps with the unlink instead, see
+    /// [`List::locate_pred`]. If `node` has no children past `dp`, the
+    /// pointer to it is swung to its successor `node.children[dp]`.
+    /// Otherwise a placeholder takes its place: a node with the same
+    /// coordinates and no element, which adopts all of its children and is
+    /// marked with `DEL` from the start. Every helper builds its own, only
+    /// the one that wins the CAS is linked. A placeholder is unlinked in turn
+    /// once it has no children left.
+    ///
+    /// Until then an insert may still go in front of `node` and adopt some
+    /// of its frozen pointers, or replace it, in which case the insert
+    /// reclaims it.
+    unsafe fn unlink<'g>(&'g self, mut node: Shared<'g, Node<DIM>>, guard: &'g Guard) {
+        loop {
+            let n = node.deref();
+            // Whatever the node adopts has to be in place before it is frozen
+            let ad = n.adesc.load(Acquire, guard);
+            if !ad.is_null() {
+                self.finish_inserting(node, ad, guard);
+            }
+
+            // The dimension a node is reached through only grows, so the
+            // pointers frozen on the first attempt cover every later one
+            let mut frozen = DIM;
+            let p = loop {
+                let p = self.locate_pred(n.coords, guard);
+                // Unlinked already, or replaced by an insert
+                if p.dc != DIM || p.curr.as_raw() != node.as_raw() {
+                    return;
+                }
+                if p.dp < frozen {
+                    n.children.freeze(p.dp, guard);
+                    frozen = p.dp;
+                }
+
+                let top = (p.dp + 1..DIM)
+                    .rev()
+                    .find(|&d| !n.children.load(d, Acquire, guard).is_null());
+                let next = match top {
+                    // An empty pointer keeps the mark, see `finish_inserting`
+                    None => match n.children.load(p.dp, Acquire, guard) {
+                        next if next.is_null() => Shared::null().with_tag(Self::DEL),
+                        next => next.with_tag(next.tag() & Self::DEL),
+                    },
+                    Some(top) => {
+                        let placeholder = Owned::new(Node::new(n.coords));
+                        placeholder.children.reset(p.dp, guard);
+                        for d in p.dp..=top {
+                            let child = n.children.load(d, Acquire, guard);
+                            if !child.is_null() {
+                                placeholder
+                                    .children
+                                    .slot(d, guard)
+                                    .store(child.with_tag(child.tag() & Self::DEL), Relaxed);
+                            }
+                        }
+                        placeholder
+                            .adesc
+                            .store(Shared::null().with_tag(Node::<DIM>::PLACEHOLDER), Relaxed);
+                        placeholder.into_shared(guard).with_tag(Self::DEL)
+                    }
+                };
+
+                // Fails if the predecessor was adopted or frozen, if an
+                // insert went in front of `node`, or if `node` is gone
+                if p.pred
+                    .deref()
+                    .children
+                    .slot(p.dp, guard)
+                    .compare_and_set(node.with_tag(Self::DEL), next, SeqCst, guard)
+                    .is_ok()
+                {
+                    break p;
+                }
+                if top.is_some() {
+                    drop(next.into_owned());
+                }
+            };
+            Self::retire(n, guard);
+
+            // A placeholder that lost its last child is unlinked as well
+            if !self.is_bare(p.pred, guard) {
+                return;
+            }
+            node = p.pred;
+        }
+    }
+
+    /// Returns `true` if `node` is a placeholder without children, which
+    /// whoever took away the last one has to unlink.
+    unsafe fn is_bare<'g>(&'g self, node: Shared<'g, Node<DIM>>, guard: &'g Guard) -> bool {
+        let n = node.deref();
+        if !n.is_placeholder(guard) {
+            return false;
+        }
+        // Two threads that each take away one of the last two children
+        // must not both see the other one still there
+        let p = self.locate_pred(n.coords, guard);
+        p.dc == DIM
+            && p.curr.as_raw() == node.as_raw()
+            && (p.dp + 1..DIM).all(|d| n.children.load(d, SeqCst, guard).is_null())
+    }
+
+    /// Hands an unlinked node to the collector.
+    unsafe fn retire(node: &Node<DIM>, guard: &Guard) {
+        if node.is_placeholder(guard) {
+            guard.defer_destroy(Shared::from(node as *const _));
+        } else {
+            C::finalize(node, guard);
+        }
+    }
+
+    pub(crate) unsafe fn insert<'g>(
+        &'g self,
+        container: impl Into<Element<'g, T, C>>,
+        guard: &'g Guard,
+    ) {
+        let _ = self.insert_with(container.into(), guard, |_| true);
+    }
+
+    /// Inserts `container` unless a node with the same coordinates is
+    /// present. In that case the present node is returned and `container` is
+    /// left to the caller.
+    pub(crate) unsafe fn insert_if_absent<'g>(
+        &'g self,
+        container: Element<'g, T, C>,
+        guard: &'g Guard,
+    ) -> Result<(), &'g T> {
+        match self.insert_with(container, guard, |present| present.is_none()) {
+            Ok(_) => Ok(()),
+            Err(present) => Err(present.unwrap()),
+        }
+    }
+
+    /// Inserts `container` if `replace` accepts the node that is present at
+    /// its coordinates, or `None` if there is none.
+    ///
+    /// `replace` is called again whenever the CAS on the predecessor fails,
+    /// since the present node may have changed. A successful CAS swaps out
+    /// exactly the node that was accepted, so this is a compare-and-swap on
+    /// the node. Returns the replaced node, or the rejected one in which case
+    /// `container` is left to the caller.
+    pub(crate) unsafe fn insert_with<'g, F>(
+        &'g self,
+        container: Element<'g, T, C>,
+        guard: &'g Guard,
+        replace: F,
+    ) -> Result<Option<&'g T>, Option<&'g T>>
+    where
+        F: FnMut(Option<&'g T>) -> bool,
+    {
+        let coords = C::entry_of(container.deref()).coords;
+        self.insert_at(container, self.locate_pred(coords, guard), guard, replace)
+    }
+
+    /// Same as [`List::insert_with`], but starts from `p`, which an earlier
+    /// call to `locate_pred` returned for the coordinates of `container`.
+    /// The position is only located again if the CAS fails.
+    pub(crate) unsafe fn insert_at<'g, F>(
+        &'g self,
+        container: Element<'g, T, C>,
+        mut p: Pred<'g, DIM>,
+        guard: &'g Guard,
+        mut replace: F,
+    ) -> Result<Option<&'g T>, Option<&'g T>>
+    where
+        F: FnMut(Option<&'g T>) -> bool,
+    {
+        let mut ad = Shared::null();
+        let entry: &Node<DIM> = C::entry_of(container.deref());
+        loop {
+            let present = Self::present(&p);
+            if !replace(present) {
+                return Err(present);
+            }
+
+            // If we found some node, load the adoption description
+            if let Some(curr) = p.curr.as_ref() {
+                ad = curr.adesc.load(Relaxed, guard);
+            }
+
+            // If we have some adoption description, we help finish insertion
+            if !ad.is_null() && p.dp != p.dc {
+                self.finish_inserting(p.curr, ad, guard);
+            }
+
+            // Only keep the deletion mark, the insert must fail if the
+            // pointer to `curr` has been adopted or frozen
+            p.curr = p.curr.with_tag(p.curr.tag() & Self::DEL);
+
+            // A deleted node that only differs in the last dimension is
+            // purged by adopting all of its children
+            if !p.curr.is_null() && (p.curr.tag() & Self::DEL) != 0 && p.dc == DIM - 1 {
+                p.dc = DIM;
+            }
+
+            ad = Shared::null();
+
+            if p.dp != p.dc {
+                ad = Owned::new(AdoptDesc {
+                    curr: Atomic::from(p.curr.with_tag(Self::UNMARKED)),
+                    dp: p.dp,
+                    dc: p.dc,
+                })
+                .into_shared(guard);
+            }
+
+            // Dimensions before `dp` read as adopted from here on. This also
+            // clears what an earlier attempt left behind, a stale child would
+            // be linked twice or keep the node from adopting the real one.
+            entry.children.reset(p.dp, guard);
+
+            if p.dc < DIM {
+                entry.children.slot(p.dc, guard).store(p.curr, Relaxed);
+            }
+
+            entry.adesc.store(ad, Relaxed);
+
+            let entry_ptr = Shared::from(entry as *const _);
+            if p.pred
+                .deref()
+                .children
+                .slot(p.dp, guard)
+                .compare_and_set_weak(p.curr, entry_ptr, Release, guard)
+                .is_ok()
+            {
+                if !ad.is_null() {
+                    self.finish_inserting(entry_ptr, ad, guard);
+                }
+
+                // `curr` was replaced, either because it had the same
+                // coordinates or because it was purged. Otherwise it may
+                // be a placeholder whose children were all adopted.
+                if p.dc == DIM {
+                    Self::retire(p.curr.deref(), guard);
+                } else if !p.curr.is_null() && self.is_bare(p.curr, guard) {
+                    self.unlink(p.curr.with_tag(Self::UNMARKED), guard);
+                }
+                return Ok(present);
+            }
+
+            // The descriptor was never published, so we can drop it right away
+            if !ad.is_null() {
+                drop(ad.into_owned());
+            }
+
+            // Locate the position to insert the node at again
+            p = self.locate_pred(entry.coords, guard);
+        }
+    }
+
+    /// Iterates over the nodes with coordinates between `lo` and `hi`, in
+    /// lexicographic order, see [`Iter`].
+    pub fn range<'g>(
+        &'g self,
+        lo: Bound<[u8; DIM]>,
+        hi: Bound<[u8; DIM]>,
+        guard: &'g Guard,
+    ) -> Iter<'g, DIM, T, C> {
+        let head = self.head.load(Relaxed, guard);
+        Iter {
+            guard,
+            root: unsafe { head.deref().children.load(0, Acquire, guard) },
+            lo,
+            hi,
+            front: None,
+            back: None,
+            last_front: None,
+            last_back: None,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn starts_with<'g>(&'g self, needle: &[u8], guard: &'g Guard) -> Iter<'g, DIM, T, C> {
+        let (mut lo, mut hi) = ([0; DIM], [u8::MAX; DIM]);
+        lo[..needle.len()].copy_from_slice(needle);
+        hi[..needle.len()].copy_from_slice(needle);
+
+        self.range(Bound::Included(lo), Bound::Included(hi), guard)
+    }
+}
+
+impl<const DIM: usize, T, C: IsElement<DIM, T>> List<DIM, T, C> {
+    /// Moves the elements out of the list, leaving it empty, and hands the
+    /// ones that are not deleted to `f` along with their coordinates, in no
+    /// particular order.
+    pub(crate) fn drain(&mut self, f: impl FnMut([u8; DIM], T)) {
+        unsafe {
+            let head = self
+                .head
+                .swap(Owned::new(Node::default()), Relaxed, unprotected());
+            Self::reclaim(head, f);
+        }
+    }
+
+    /// Reclaims the nodes reachable from `head` right away, handing the
+    /// elements that are not deleted to `f`. Nobody else may access them.
+    unsafe fn reclaim(head: Shared<'_, Node<DIM>>, mut f: impl FnMut([u8; DIM], T)) {
+        let guard = unprotected();
+        let mut stack = vec![(head, 0)];
+        while let Some((node, dim)) = stack.pop() {
+            let n = node.deref();
+            for d in dim..DIM {
+                let child = n.children.load(d, Relaxed, guard);
+                if !child.is_null() && (child.tag() & Self::ADP) == 0 {
+                    stack.push((child, d));
+                }
+            }
+
+            let ad = n.adesc.load(Relaxed, guard);
+            if !ad.is_null() {
+                drop(ad.into_owned());
+            }
+
+            if node == head || n.is_placeholder(guard) {
+                drop(node.into_owned());
+            } else if node.tag() & Self::DEL != 0 {
+                C::finalize(n, guard);
+            } else {
+                f(n.coords, C::take(n));
+            }
+        }
+    }
+}
+
+impl<const DIM: usize, T, C: IsElement<DIM, T>> Drop for List<DIM, T, C> {
+    fn drop(&mut self) {
+        // Nobody else can access the list, so the nodes are reclaimed
+        // immediately through the unprotected guard
+        unsafe { Self::reclaim(self.head.load(Relaxed, unprotected()), |_, _| ()) }
+    }
+}
+
+/// Entries of an [`MdList`] along with the chunks of their keys
+type Entries<'g, const DIM: usize, T> =
+    Box<dyn 'g + DoubleEndedIterator<Item = (Vec<[u8; DIM]>, &'g T)>>;
+
+/// A concurrent ordered map on top of [`List`].
+///
+/// Keys are encoded with [`ToCoords`]. Keys that do not fit in `DIM`
+/// dimensions are split into chunks, see [`ToCoords::to_chunks`]. Keys with a
+/// single chunk are stored in `list`, longer keys continue in a nested map,
+/// stored in `overflow` under their first chunk. Nested maps are never
+/// removed, so a lookup only has to follow the chain down to the last chunk,
+/// and an insert never loses its value to a nested map that is being
+/// removed. A map that runs empty is kept for the next key with its prefix,
+/// so there are never more nested maps than distinct leading chunks of the
+/// keys that were inserted.
+pub struct MdList<K, T, const DIM: usize = 16> {
+    list: Values<DIM, T>,
+    overflow: Values<DIM, MdList<K, T, DIM>>,
+    _ph: core::marker::PhantomData<K>,
+}
+
+impl<K, T, const DIM: usize> Default for MdList<K, T, DIM> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const DIM: usize, K, T> MdList<K, T, DIM> {
+    pub fn new() -> Self {
+        Self {
+            list: List::new(),
+            overflow: List::new(),
+            _ph: core::marker::PhantomData,
+        }
+    }
+
+    /// Inserts `value` at `key`, replacing any previous value.
+    ///
+    /// A replaced value is reclaimed once no pinned thread can observe it.
+    #[inline]
+    pub fn insert<Q: ToCoords<DIM>>(&self, key: Q, value: T) {
+        self.insert_chunks(&key.to_chunks(), value)
+    }
+
+    fn insert_chunks(&self, chunks: &[[u8; DIM]], value: T) {
+        let guard = &pin();
+        let (last, init) = chunks.split_last().unwrap();
+        let level = self.nested_or_insert(init, guard);
+        unsafe {
+            let elem = Alloc::alloc(NodeWithValue::new(*last, value), guard);
+            level.list.insert(elem, guard)
+        }
+    }
+
+    /// Returns the value at `key`, which stays valid for as long as `guard`
+    /// is pinned.
+    #[inline]
+    pub fn get<'g, Q: ToCoords<DIM>>(&'g self, key: Q, guard: &'g Guard) -> Option<&'g T> {
+        let chunks = key.to_chunks();
+        let (last, init) = chunks.split_last().unwrap();
+        let level = self.nested(init, guard)?;
+        unsafe { level.list.get(*last, guard).map(|v| v.deref()) }
+    }
+
+    /// Removes the value at `key`. The returned value stays valid for as long
+    /// as `guard` is pinned, after that it is reclaimed.
+    #[inline]
+    pub fn remove<'g, Q: ToCoords<DIM>>(&'g self, key: Q, guard: &'g Guard) -> Option<&'g T> {
+        self.remove_chunks(&key.to_chunks(), guard)
+    }
+
+    fn remove_chunks<'g>(&'g self, chunks: &[[u8; DIM]], guard: &'g Guard) -> Option<&'g T> {
+        let (last, init) = chunks.split_last().unwrap();
+        let level = self.nested(init, guard)?;
+        unsafe { level.list.remove(*last, guard).map(|v| v.deref()) }
+    }
+
+    /// Looks up `key` once, returning a handle to insert or modify its value
+    /// in place, see [`Entry`].
+    pub fn entry<'g, Q: ToCoords<DIM>>(&'g self, key: Q, guard: &'g Guard) -> Entry<'g, K, T, DIM> {
+        let chunks = key.to_chunks();
+        let (last, init) = chunks.split_last().unwrap();
+        let Some(level) = self.nested(init, guard) else {
+            return Entry::Vacant(VacantEntry::new(self, chunks, None, guard));
+        };
+
+        let last = *last;
+        unsafe {
+            let p = level.list.locate_pred(last, guard);
+            match Values::<DIM, T>::present(&p) {
+                Some(node) => Entry::Occupied(OccupiedEntry::new(level, last, node, guard)),
+                None => Entry::Vacant(VacantEntry::new(self, chunks, Some((level, p)), guard)),
+            }
+        }
+    }
+
+    /// Inserts `value` at `key` unless a value is present. Returns the
+    /// inserted value, or the present one in which case `value` is dropped.
+    pub fn insert_if_absent<'g, Q: ToCoords<DIM>>(
+        &'g self,
+        key: Q,
+        value: T,
+        guard: &'g Guard,
+    ) -> Result<&'g T, &'g T> {
+        let chunks = key.to_chunks();
+        let (last, init) = chunks.split_last().unwrap();
+        let level = self.nested_or_insert(init, guard);
+        unsafe {
+            let elem = Alloc::alloc(NodeWithValue::new(*last, value), guard);
+            match level.list.insert_if_absent(elem, guard) {
+                Ok(()) => Ok(elem.deref()),
+                Err(present) => {
+                    Alloc::dealloc(elem);
+                    Err(present)
+                }
+            }
+        }
+    }
+
+    /// Replaces the value at `key` with `new` if it is equal to `expected`.
+    ///
+    /// Returns the replaced value, or the present value (if any) in which
+    /// case `new` is dropped.
+    pub fn compare_and_swap<'g, Q: ToCoords<DIM>>(
+        &'g self,
+        key: Q,
+        expected: &T,
+        new: T,
+        guard: &'g Guard,
+    ) -> Result<&'g T, Option<&'g T>>
+    where
+        T: PartialEq,
+    {
+        let chunks = key.to_chunks();
+        let (last, init) = chunks.split_last().unwrap();
+        let level = self.nested(init, guard).ok_or(None)?;
+        unsafe {
+            let elem = Alloc::alloc(NodeWithValue::new(*last, new), guard);
+            let swapped = level.list.insert_with(elem, guard, |present| {
+                present.is_some_and(|present| **present == *expected)
+            });
+            match swapped {
+                Ok(replaced) => Ok(replaced.unwrap().deref()),
+                Err(present) => {
+                    Alloc::dealloc(elem);
+                    Err(present.map(|v| v.deref()))
+                }
+            }
+        }
+    }
+
+    /// Replaces the value at `key` with `f` applied to it, returning the new
+    /// value, or `None` if `key` is absent.
+    ///
+    /// If the value changes between reading it and swapping in the result,
+    /// `f` is applied again to the changed value, so `f` may be called more
+    /// than once.
+    pub fn update<'g, Q, F>(&'g self, key: Q, mut f: F, guard: &'g Guard) -> Option<&'g T>
+    where
+        Q: ToCoords<DIM>,
+        F: FnMut(&T) -> T,
+    {
+        let chunks = key.to_chunks();
+        let (last, init) = chunks.split_last().unwrap();
+        let level = self.nested(init, guard)?;
+        unsafe {
+            let mut current = level.list.get(*last, guard)?;
+            loop {
+                let elem = Alloc::alloc(NodeWithValue::new(*last, f(current)), guard);
+                let swapped = level.list.insert_with(elem, guard, |present| {
+                    present.is_some_and(|present| core::ptr::eq(present, current))
+                });
+                match swapped {
+                    Ok(_) => return Some(elem.deref()),
+                    Err(present) => {
+                        Alloc::dealloc(elem);
+                        current = present?;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the entries with keys in `range`, in key order.
+    pub fn range<'g, R: RangeBounds<K>>(
+        &'g self,
+        range: R,
+        guard: &'g Guard,
+    ) -> impl 'g + DoubleEndedIterator<Item = (K, &'g T)>
+    where
+        K: Clone + ToCoords<DIM> + FromCoords<DIM>,
+    {
+        self.chunks_range(
+            range.start_bound().cloned().map(K::to_chunks),
+            range.end_bound().cloned().map(K::to_chunks),
+            guard,
+        )
+        .map(Self::decode)
+    }
+
+    /// Returns the entries whose coordinates start with `prefix`, in key
+    /// order. The prefix may be longer than `DIM`.
+    pub fn starts_with<'g, Q: ?Sized + AsRef<[u8]>>(
+        &'g self,
+        prefix: &Q,
+        guard: &'g Guard,
+    ) -> impl 'g + DoubleEndedIterator<Item = (K, &'g T)>
+    where
+        K: FromCoords<DIM>,
+    {
+        self.chunks_starting_with(prefix.as_ref(), guard)
+            .map(Self::decode)
+    }
+
+    /// Returns the entries whose coordinates lie between `lo` and `hi` in
+    /// every dimension, see [`List::query_box`].
+    ///
+    /// Only the first chunk of a key is matched, so a key longer than `DIM`
+    /// is returned if its first chunk lies in the box. The entries come in
+    /// lexicographic order, except that longer keys follow all of the keys
+    /// that fit in `DIM` dimensions.
+    pub fn query_box<'g>(
+        &'g self,
+        lo: [u8; DIM],
+        hi: [u8; DIM],
+        guard: &'g Guard,
+    ) -> impl 'g + Iterator<Item = (K, &'g T)>
+    where
+        K: FromCoords<DIM>,
+    {
+        let keys = self
+            .list
+            .query_box(lo, hi, guard)
+            .map(|n| (vec![n.node.coords], n.deref()));
+
+        let longer = self.overflow.query_box(lo, hi, guard).flat_map(move |n| {
+            let chunk = n.node.coords;
+            n.deref()
+                .chunks_range(Bound::Unbounded, Bound::Unbounded, guard)
+                .map(move |entry| Self::prefixed(chunk, entry))
+        });
+
+        keys.chain(longer).map(Self::decode)
+    }
+
+    /// Returns the `k` entries closest to `query` under `metric`, along with
+    /// their distance, closest first, see [`List::nearest`].
+    ///
+    /// Distances are only defined for keys that fit in `DIM` dimensions, so
+    /// longer keys are not considered.
+    pub fn nearest<'g>(
+        &'g self,
+        query: [u8; DIM],
+        k: usize,
+        metric: Metric,
+        guard: &'g Guard,
+    ) -> Vec<(u64, K, &'g T)>
+    where
+        K: FromCoords<DIM>,
+    {
+        self.list
+            .nearest(query, k, metric, guard)
+            .into_iter()
+            .map(|(dist, n)| (dist, K::from_coords(n.node.coords), n.deref()))
+            .collect()
+    }
+
+    /// Returns a cursor before the first entry, see [`Cursor`].
+    pub fn cursor<'g>(&'g self, guard: &'g Guard) -> Cursor<'g, K, T, DIM>
+    where
+        K: FromCoords<DIM>,
+    {
+        Cursor::new(self, guard)
+    }
+
+    pub fn iter<'g>(&'g self, guard: &'g Guard) -> impl 'g + DoubleEndedIterator<Item = (K, &'g T)>
+    where
+        K: FromCoords<DIM>,
+    {
+        self.chunks_range(Bound::Unbounded, Bound::Unbounded, guard)
+            .map(Self::decode)
+    }
+
+    /// Decodes the key of an iterated entry
+    #[inline]
+    fn decode((chunks, value): (Vec<[u8; DIM]>, &T)) -> (K, &T)
+    where
+        K: FromCoords<DIM>,
+    {
+        (K::from_chunks(&chunks), value)
+    }
+
+    /// Follows the nested maps for `chunks`, if they exist.
+    fn nested<'g>(&'g self, chunks: &[[u8; DIM]], guard: &'g Guard) -> Option<&'g Self> {
+        let mut level = self;
+        for chunk in chunks {
+            level = unsafe { level.overflow.get(*chunk, guard)?.deref() };
+        }
+        Some(level)
+    }
+
+    /// Follows the nested maps for `chunks`, creating the missing ones.
+    fn nested_or_insert<'g>(&'g self, chunks: &[[u8; DIM]], guard: &'g Guard) -> &'g Self {
+        let mut level = self;
+        for chunk in chunks {
+            let nested = match unsafe { level.overflow.get(*chunk, guard) } {
+                Some(nested) => nested,
+                None => unsafe {
+                    let elem = Alloc::alloc(NodeWithValue::new(*chunk, Self::new()), guard);
+                    match level.overflow.insert_if_absent(elem, guard) {
+                        Ok(()) => elem.deref(),
+                        Err(present) => {
+                            // Somebody else created it first
+                            Alloc::dealloc(elem);
+                            present
+                        }
+                    }
+                },
+            };
+            level = nested.deref();
+        }
+        level
+    }
+
+    /// Returns the entries with chunked keys between `lo` and `hi`.
+    ///
+    /// A key that ends in this map sorts before the longer keys that share
+    /// its chunk, so those are merged in after it.
+    fn chunks_range<'g>(
+        &'g self,
+        lo: Bound<Vec<[u8; DIM]>>,
+        hi: Bound<Vec<[u8; DIM]>>,
+        guard: &'g Guard,
+    ) -> Entries<'g, DIM, T> {
+        // Single chunk keys are compared against the first chunk of the
+        // bounds. A longer bound lies past the key made of its first chunk.
+        let list_lo = match &lo {
+            Bound::Included(lo) if lo.len() == 1 => Bound::Included(lo[0]),
+            Bound::Included(lo) | Bound::Excluded(lo) => Bound::Excluded(lo[0]),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let list_hi = match &hi {
+            Bound::Excluded(hi) if hi.len() == 1 => Bound::Excluded(hi[0]),
+            Bound::Included(hi) | Bound::Excluded(hi) => Bound::Included(hi[0]),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        // Nested maps are bounded by the rest of the bounds if their chunk is
+        // the first one of the bounds. All their keys lie past a single
+        // chunk bound.
+        let overflow_lo = match &lo {
+            Bound::Included(lo) | Bound::Excluded(lo) => Bound::Included(lo[0]),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let overflow_hi = match &hi {
+            Bound::Included(hi) | Bound::Excluded(hi) if hi.len() == 1 => Bound::Excluded(hi[0]),
+            Bound::Included(hi) | Bound::Excluded(hi) => Bound::Included(hi[0]),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        let keys = self
+            .list
+            .range(list_lo, list_hi, guard)
+            .map(|n| (vec![n.node.coords], n.deref()));
+
+        let longer = self
+            .overflow
+            .range(overflow_lo, overflow_hi, guard)
+            .flat_map(move |n| {
+                let chunk = n.node.coords;
+                let rest = |bound: &Bound<Vec<[u8; DIM]>>| match bound {
+                    Bound::Included(b) if b.len() > 1 && b[0] == chunk => {
+                        Bound::Included(b[1..].to_vec())
+                    }
+                    Bound::Excluded(b) if b.len() > 1 && b[0] == chunk => {
+                        Bound::Excluded(b[1..].to_vec())
+                    }
+                    _ => Bound::Unbounded,
+                };
+                n.deref()
+                    .chunks_range(rest(&lo), rest(&hi), guard)
+                    .map(move |entry| Self::prefixed(chunk, entry))
+            });
+
+        Box::new(Merge::new(keys, longer))
+    }
+
+    /// Returns the entries with chunked keys whose coordinates start with
+    /// `prefix`.
+    fn chunks_starting_with<'g>(&'g self, prefix: &[u8], guard: &'g Guard) -> Entries<'g, DIM, T> {
+        if prefix.len() > DIM {
+            let (first, rest) = prefix.split_at(DIM);
+            let chunk: [u8; DIM] = first.try_into().unwrap();
+            return match unsafe { self.overflow.get(chunk, guard) } {
+                Some(nested) => Box::new(
+                    nested
+                        .deref()
+                        .chunks_starting_with(rest, guard)
+                        .map(move |entry| Self::prefixed(chunk, entry)),
+                ),
+                None => Box::new(core::iter::empty()),
+            };
+        }
+
+        let keys = self
+            .list
+            .starts_with(prefix, guard)
+            .map(|n| (vec![n.node.coords], n.deref()));
+
+        let longer = self.overflow.starts_with(prefix, guard).flat_map(move |n| {
+            let chunk = n.node.coords;
+            n.deref()
+                .chunks_range(Bound::Unbounded, Bound::Unbounded, guard)
+                .map(move |entry| Self::prefixed(chunk, entry))
+        });
+
+        Box::new(Merge::new(keys, longer))
+    }
+
+    /// Puts the chunk of a nested map in front of the key of one of its entries
+    #[inline]
+    fn prefixed(
+        chunk: [u8; DIM],
+        (mut chunks, value): (Vec<[u8; DIM]>, &T),
+    ) -> (Vec<[u8; DIM]>, &T) {
+        chunks.insert(0, chunk);
+        (chunks, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::ebr::collector::Collector;
+    use crate::ebr::Owned;
+
+    #[test]
+    fn insert() {
+        let collector = Collector::new();
+        let handle = collector.register();
+        let guard = handle.pin();
+
+        let l: List<4, Node<4>> = List::new();
+
+        let e1 = Owned::new(Node::new([1, 0, 0, 0])).into_shared(&guard);
+        let e2 = Owned::new(Node::new([2, 0, 0, 0])).into_shared(&guard);
+        let e3 = Owned::new(Node::new([3, 0, 0, 0])).into_shared(&guard);
+
+        unsafe {
+            l.insert(e1, &guard);
+            l.insert(e2, &guard);
+            l.insert(e3, &guard);
+            assert_eq!(
+                l.get([1, 0, 0, 0], &guard).unwrap() as *const Node<4>,
+                e1.as_raw()
+            );
+            assert_eq!(
+                l.get([2, 0, 0, 0], &guard).unwrap() as *const Node<4>,
+                e2.as_raw()
+            );
+            assert_eq!(
+                l.get([3, 0, 0, 0], &guard).unwrap() as *const Node<4>,
+                e3.as_raw()
+            );
+        }
+    }
+
+    #[test]
+    fn insert_with_value() {
+        let collector = Collector::new();
+        let handle = collector.register();
+        let guard = handle.pin();
+
+        let l: List<4, NodeWithValue<4, usize>> = List::new();
+
+        let e1 = Owned::new(NodeWithValue::new([1, 0, 0, 0], 10)).into_shared(&guard);
+        let e2 = Owned::new(NodeWithValue::new([2, 0, 0, 0], 20)).into_shared(&guard);
+        let e3 = Owned::new(NodeWithValue::new([3, 0, 0, 0], 30)).into_shared(&guard);
+
+        let e4 = Owned::new(NodeWithValue::new([0, 1, 0, 0], 40)).into_shared(&guard);
+        let e5 = Owned::new(NodeWithValue::new([1, 1, 0, 0], 50)).into_shared(&guard);
+        let e6 = Owned::new(NodeWithValue::new([2, 1, 0, 0], 60)).into_shared(&guard);
+
+        unsafe {
+            l.insert(e1, &guard);
+            l.insert(e2, &guard);
+            l.insert(e3, &guard);
+            l.insert(e4, &guard);
+            l.insert(e5, &guard);
+            l.insert(e6, &guard);
+            assert_eq!(l.get([1, 0, 0, 0], &guard).unwrap().value, 10);
+            assert_eq!(l.get([2, 0, 0, 0], &guard).unwrap().value, 20);
+            assert_eq!(l.get([3, 0, 0, 0], &guard).unwrap().value, 30);
+            assert_eq!(l.get([0, 1, 0, 0], &guard).unwrap().value, 40);
+            assert_eq!(l.get([1, 1, 0, 0], &guard).unwrap().value, 50);
+            assert_eq!(l.get([2, 1, 0, 0], &guard).unwrap().value, 60);
+        }
+    }
+
+    #[test]
+    fn test_iter() {
+        let collector = Collector::new();
+        let handle = collector.register();
+        let guard = handle.pin();
+
+        let l = List::<16, _, NodeWithValue<16, _>>::new();
+
+        let e1 = Owned::new(NodeWithValue::new(ToCoords::to_coords(1), 1)).into_shared(&guard);
+        let e2 = Owned::new(NodeWithValue::new(ToCoords::to_coords(2), 2)).into_shared(&guard);
+        let e3 = Owned::new(NodeWithValue::new(ToCoords::to_coords(3), 3)).into_shared(&guard);
+        let e4 = Owned::new(NodeWithValue::new(ToCoords::to_coords(4), 4)).into_shared(&guard);
+        let e5 = Owned::new(NodeWithValue::new(ToCoords::to_coords(5), 5)).into_shared(&guard);
+        let e6 = Owned::new(NodeWithValue::new(ToCoords::to_coords(6), 6)).into_shared(&guard);
+
+        unsafe {
+            l.insert(e1, &guard);
+            l.insert(e2, &guard);
+            l.insert(e3, &guard);
+            l.insert(e4, &guard);
+            l.insert(e5, &guard);
+            l.insert(e6, &guard);
+
+            let mut iter = l.starts_with(&[], &guard);
+            dbg!(iter.next().unwrap().value);
+            dbg!(iter.next().unwrap().value);
+            dbg!(iter.next().unwrap().value);
+            dbg!(iter.next().unwrap().value);
+            dbg!(iter.next().unwrap().value);
+            dbg!(iter.next().unwrap().value);
+        }
+    }
+
+    #[test]
+    fn test_parallel() {
+        let l = MdList::<u64, u64>::new();
+
+        let keys = (300..1_000).cycle().take(16_000).collect::<Vec<_>>();
+        keys.par_iter().for_each(|i| {
+            l.insert(*i, *i);
+        });
+
+        keys.par_iter().for_each(|i| {
+            assert_eq!(l.get(*i, &pin()), Some(i), "key: {}", i);
+        });
+
+        let mut report = l.validate(&pin());
+        report.expect_keys(300..1_000_u64);
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.live.len(), 700);
+    }
+
+    /// Runs random inserts, gets and removes on `keys` from several threads,
+    /// with a unique value per insert, and checks the history.
+    fn check_linearizable<K, const DIM: usize>(keys: &[K])
+    where
+        K: ToCoords<DIM> + Clone + Eq + core::hash::Hash + core::fmt::Debug + Send + Sync,
+    {
+        use linearizability::{assert_linearizable, History};
+
+        let l = MdList::<K, u64, DIM>::new();
+        let history = History::new();
+        std::thread::scope(|s| {
+            for thread in 0..8_u64 {
+                let (l, history) = (&l, &history);
+                s.spawn(move || {
+                    let mut log = history.log();
+                    let mut rng = thread.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+                    for i in 0..2_000 {
+                        rng ^= rng << 13;
+                        rng ^= rng >> 7;
+                        rng ^= rng << 17;
+                        let key = keys[(rng >> 8) as usize % keys.len()].clone();
+                        match rng % 4 {
+                            0 | 1 => log.get(l, key),
+                            2 => log.insert(l, key, thread << 32 | i),
+                            _ => log.remove(l, key),
+                        }
+                    }
+                });
+            }
+        });
+        assert_linearizable(history);
+        assert!(l.validate(&pin()).is_ok(), "{}", l.validate(&pin()));
+    }
+
+    #[test]
+    fn test_linearizable() {
+        check_linearizable::<u64, 16>(&(0..64).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_linearizable_long_keys() {
+        let keys = (0..64)
+            .map(|i| format!("shared/{:02}", i))
+            .collect::<Vec<_>>();
+        check_linearizable::<String, 4>(&keys);
+    }
+
+    #[test]
+    fn test_sorted_iter() {
+        let l = MdList::<i64, i64>::new();
+
+        let keys = (0..1_000)
+            .map(|i| (i * 7919) % 1_000 - 500)
+            .collect::<Vec<_>>();
+        keys.par_iter().for_each(|i| {
+            l.insert(*i, *i);
+        });
+
+        assert_eq!(
+            l.iter(&pin()).map(|(k, v)| (k, *v)).collect::<Vec<_>>(),
+            (-500..500).map(|i| (i, i)).collect::<Vec<_>>()
+        );
+
+        let l = MdList::<f64, f64>::new();
+        for i in [3.5, -0.25, 1e10, -7.0, 0.0, 2.0] {
+            l.insert(i, i);
+        }
+        assert_eq!(
+            l.iter(&pin()).map(|(k, _)| k).collect::<Vec<_>>(),
+            vec![-7.0, -0.25, 0.0, 2.0, 3.5, 1e10]
+        );
+    }
+
+    #[test]
+    fn test_lexicographic_order() {
+        let guard = &pin();
+        let l = MdList::<[u8; 4], [u8; 4], 4>::new();
+
+        // Includes the all-zero key, which used to clash with the head
+        let mut keys = (0..2_000_u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) % 20_000).to_be_bytes())
+            .collect::<Vec<_>>();
+        keys.extend([[0; 4], [0, 0, 0, 1], [u8::MAX; 4]]);
+        keys.par_iter().for_each(|k| {
+            l.insert(*k, *k);
+        });
+
+        keys.sort();
+        keys.dedup();
+        assert_eq!(l.get([0; 4], guard), Some(&[0; 4]));
+        assert!(l.iter(guard).all(|(k, v)| k == *v));
+        assert_eq!(l.iter(guard).map(|(k, _)| k).collect::<Vec<_>>(), keys);
+        assert_eq!(
+            l.iter(guard).rev().map(|(k, _)| k).collect::<Vec<_>>(),
+            keys.iter().rev().copied().collect::<Vec<_>>()
+        );
+
+        // Both ends stop when they meet
+        let mut iter = l.iter(guard);
+        let mut front = vec![];
+        let mut back = vec![];
+        loop {
+            match (iter.next(), iter.next_back()) {
+                (Some((f, _)), Some((b, _))) => {
+                    front.push(f);
+                    back.push(b);
+                }
+                (Some((f, _)), None) => front.push(f),
+                (None, _) => break,
+            }
+        }
+        front.extend(back.into_iter().rev());
+        assert_eq!(front, keys);
+
+        let (lo, hi) = ([0, 0, 10, 0], [0, 0, 40, 0]);
+        assert_eq!(
+            l.range(lo..hi, guard)
+                .rev()
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>(),
+            keys.iter()
+                .filter(|k| (lo..hi).contains(*k))
+                .rev()
+                .copied()
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(l.remove([0; 4], guard), Some(&[0; 4]));
+        assert_eq!(l.iter(guard).next(), Some(([0, 0, 0, 1], &[0, 0, 0, 1])));
+    }
+
+    #[test]
+    fn test_remove() {
+        let guard = &pin();
+        let l = MdList::<u64, u64>::new();
+        for i in 1..100 {
+            l.insert(i, i);
+        }
+
+        // Even keys are removed, some of them still route to odd keys
+        for i in (2..100).step_by(2) {
+            assert_eq!(l.remove(i, guard), Some(&i));
+            assert_eq!(l.remove(i, guard), None);
+        }
+
+        for i in 1..100 {
+            if i % 2 == 0 {
+                assert_eq!(l.get(i, guard), None, "key: {}", i);
+            } else {
+                assert_eq!(l.get(i, guard), Some(&i), "key: {}", i);
+            }
+        }
+
+        assert_eq!(
+            l.iter(guard).map(|(k, _)| k).collect::<Vec<_>>(),
+            (1..100).step_by(2).collect::<Vec<_>>()
+        );
+
+        // Removed keys can be inserted again
+        l.insert(2, 20);
+        assert_eq!(l.get(2, guard), Some(&20));
+    }
+
+    #[test]
+    fn test_parallel_remove() {
+        let l = MdList::<u64, u64>::new();
+
+        let keys = (1..2_000).collect::<Vec<_>>();
+        keys.par_iter().for_each(|i| {
+            l.insert(*i, *i);
+        });
+
+        keys.par_iter().filter(|i| *i % 3 != 0).for_each(|i| {
+            assert_eq!(l.remove(*i, &pin()), Some(i), "key: {}", i);
+        });
+
+        assert_eq!(l.iter(&pin()).count(), keys.len() / 3);
+
+        let mut report = l.validate(&pin());
+        report.expect_keys(keys.iter().filter(|i| *i % 3 == 0).copied());
+        assert!(report.is_ok(), "{}", report);
+    }
+
+    #[test]
+    fn test_parallel_churn() {
+        let l = MdList::<u64, u64>::new();
+        for i in 0..2_000 {
+            l.insert(i, i);
+        }
+
+        // Removes unlink the nodes that inserts go in front of, and the
+        // other way around
+        (0..4_000u64).into_par_iter().for_each(|i| {
+            if i < 2_000 {
+                assert_eq!(l.remove(i, &pin()), Some(&i), "key: {}", i);
+            } else {
+                l.insert(i, i);
+            }
+        });
+
+        let report = l.validate(&pin());
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.live.len(), 2_000);
+
+        // Placeholders go away along with their last child
+        (2_000..4_000u64).into_par_iter().for_each(|i| {
+            assert_eq!(l.remove(i, &pin()), Some(&i), "key: {}", i);
+        });
+        let report = l.validate(&pin());
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!((report.live.len(), report.routing), (0, 0));
+    }
+
+    #[test]
+    fn test_reclaim() {
+        let value = Arc::new(());
+        let l = MdList::<u64, Arc<()>>::new();
+
+        for i in 1..100 {
+            l.insert(i, value.clone());
+        }
+
+        let collect = |expected| {
+            for _ in 0..1_000 {
+                if Arc::strong_count(&value) == expected {
+                    break;
+                }
+                pin().flush();
+                std::thread::yield_now();
+            }
+            assert_eq!(Arc::strong_count(&value), expected);
+        };
+
+        // Overwritten values are handed to the collector
+        for i in 1..100 {
+            l.insert(i, value.clone());
+        }
+        collect(100);
+
+        // Removed values are handed to the collector too, including those
+        // of nodes that had children
+        for i in 1..50 {
+            l.remove(i, &pin());
+        }
+        collect(51);
+        assert!(l.validate(&pin()).is_ok());
+
+        drop(l);
+        collect(1);
+    }
+
+    #[test]
+    fn test_string_coords() {
+        let guard = &pin();
+        let l = MdList::<String, &'static str, 32>::new();
+        l.insert("user", "user");
+
+        l.insert("user#123", "user#123");
+        l.insert("user#456", "user#456");
+        l.insert("a", "a");
+        l.insert("ab", "ab");
+        l.insert("abc", "abc");
+        l.insert("b", "b");
+        l.insert("bc", "bc");
+        l.insert("ba", "ba");
+        l.insert("br", "br");
+
+        assert_eq!(
+            l.starts_with("user#", guard)
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>(),
+            vec!["user#123", "user#456"]
+        );
+
+        assert_eq!(
+            l.starts_with("a", guard)
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>(),
+            vec!["a", "ab", "abc"]
+        );
+
+        assert_eq!(
+            l.starts_with("ba", guard)
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>(),
+            vec!["ba"]
+        );
+
+        assert_eq!(
+            l.starts_with("", guard).map(|(k, _)| k).collect::<Vec<_>>(),
+            vec!["a", "ab", "abc", "b", "ba", "bc", "br", "user", "user#123", "user#456",]
+        );
+    }
+
+    #[test]
+    fn test_range() {
+        let guard = &pin();
+        let l = MdList::<u64, u64>::new();
+        assert_eq!(l.range(.., guard).next_back(), None);
+        for i in (10..1_000).step_by(10) {
+            l.insert(i, i);
+        }
+
+        let collect =
+            |iter: &mut dyn Iterator<Item = (u64, &u64)>| iter.map(|(k, _)| k).collect::<Vec<_>>();
+        assert_eq!(
+            collect(&mut l.range(100..150, guard)),
+            [100, 110, 120, 130, 140]
+        );
+        assert_eq!(
+            collect(&mut l.range(95..=150, guard)),
+            [100, 110, 120, 130, 140, 150]
+        );
+        assert_eq!(collect(&mut l.range(975.., guard)), [980, 990]);
+        assert_eq!(collect(&mut l.range(..30, guard)), [10, 20]);
+        assert_eq!(
+            collect(&mut l.range((Bound::Excluded(10), Bound::Excluded(40)), guard)),
+            [20, 30]
+        );
+        assert_eq!(collect(&mut l.range(101..109, guard)), []);
+        assert_eq!(l.range(.., guard).count(), 99);
+
+        // Deleted nodes are skipped, but still bound the scan
+        l.remove(110_u64, guard);
+        l.remove(150_u64, guard);
+        assert_eq!(
+            collect(&mut l.range(100..=150, guard)),
+            [100, 120, 130, 140]
+        );
+
+        let l = MdList::<String, &'static str, 8>::new();
+        for key in ["2024-01-01", "2024-01-15", "2024-02-01", "2024-03-01"] {
+            l.insert(&key[2..], key);
+        }
+        assert_eq!(
+            l.range("24-01-10".to_string().."24-03".to_string(), guard)
+                .map(|(_, v)| *v)
+                .collect::<Vec<_>>(),
+            ["2024-01-15", "2024-02-01"]
+        );
+    }
+
+    #[test]
+    fn test_long_keys() {
+        let guard = &pin();
+        let l = MdList::<String, usize, 4>::new();
+        let keys = [
+            "",
+            "a",
+            "abcd",
+            "abcda",
+            "abcdabcd",
+            "abcdabcde",
+            "abcdb",
+            "abce",
+            "abcea",
+            "b",
+        ];
+        for (idx, key) in keys.iter().enumerate().rev() {
+            l.insert(*key, idx);
+        }
+
+        for (idx, key) in keys.iter().enumerate() {
+            assert_eq!(l.get(*key, guard), Some(&idx));
+        }
+        assert_eq!(l.get("abcdabc", guard), None);
+        assert_eq!(l.get("abcdabcdabcd", guard), None);
+
+        let collect = |iter: &mut dyn Iterator<Item = (String, &usize)>| {
+            iter.map(|(k, _)| k).collect::<Vec<_>>()
+        };
+        assert_eq!(collect(&mut l.iter(guard)), keys);
+        assert_eq!(
+            collect(&mut l.iter(guard).rev()),
+            keys.iter().rev().copied().collect::<Vec<_>>()
+        );
+
+        // Prefixes shorter than, equal to and longer than a chunk
+        assert_eq!(
+            collect(&mut l.starts_with("abc", guard)),
+            [
+                "abcd",
+                "abcda",
+                "abcdabcd",
+                "abcdabcde",
+                "abcdb",
+                "abce",
+                "abcea"
+            ]
+        );
+        assert_eq!(
+            collect(&mut l.starts_with("abcd", guard)),
+            ["abcd", "abcda", "abcdabcd", "abcdabcde", "abcdb"]
+        );
+        assert_eq!(
+            collect(&mut l.starts_with("abcda", guard)),
+            ["abcda", "abcdabcd", "abcdabcde"]
+        );
+        assert_eq!(
+            collect(&mut l.starts_with("abcdabcd", guard)),
+            ["abcdabcd", "abcdabcde"]
+        );
+        assert_eq!(collect(&mut l.starts_with("abcdc", guard)), [] as [&str; 0]);
+
+        // Bounds of either length split the levels
+        let range =
+            |lo: &str, hi: &str| collect(&mut l.range(lo.to_string()..hi.to_string(), guard));
+        assert_eq!(
+            range("abcd", "abcdb"),
+            ["abcd", "abcda", "abcdabcd", "abcdabcde"]
+        );
+        assert_eq!(
+            range("abcda", "abce"),
+            ["abcda", "abcdabcd", "abcdabcde", "abcdb"]
+        );
+        assert_eq!(range("abcdab", "abcdabcde"), ["abcdabcd"]);
+        assert_eq!(range("abcc", "abcdaa"), ["abcd", "abcda"]);
+        assert_eq!(
+            collect(&mut l.range("abcdabcd".to_string()..="abcea".to_string(), guard)),
+            ["abcdabcd", "abcdabcde", "abcdb", "abce", "abcea"]
+        );
+
+        assert_eq!(l.remove("abcdabcd", guard), Some(&4));
+        assert_eq!(l.remove("abcdabcd", guard), None);
+        assert_eq!(l.get("abcdabcde", guard), Some(&5));
+        assert_eq!(
+            collect(&mut l.starts_with("abcda", guard)),
+            ["abcda", "abcdabcde"]
+        );
+    }
+
+    #[test]
+    fn test_nested_maps_reused() {
+        fn nested_maps<K, T, const DIM: usize>(l: &MdList<K, T, DIM>, guard: &Guard) -> usize {
+            l.overflow
+                .range(Bound::Unbounded, Bound::Unbounded, guard)
+                .map(|n| 1 + nested_maps(n, guard))
+                .sum()
+        }
+
+        // Emptied nested maps stay behind, but keys with the same leading
+        // chunks go into them again, so churn does not add any
+        let l = Arc::new(MdList::<String, usize, 4>::new());
+        let keys = (0..200)
+            .map(|i| format!("prefix {} of a long key {}", i % 20, i))
+            .collect::<Vec<_>>();
+        // One nested map for each distinct run of leading chunks
+        let prefixes = keys
+            .iter()
+            .flat_map(|key| {
+                let chunks: Vec<[u8; 4]> = key.as_str().to_chunks();
+                (1..chunks.len()).map(move |len| chunks[..len].to_vec())
+            })
+            .collect::<std::collections::HashSet<_>>();
+        for _ in 0..20 {
+            keys.par_iter().enumerate().for_each(|(i, key)| {
+                l.insert(key.as_str(), i);
+            });
+            keys.par_iter().for_each(|key| {
+                assert!(l.remove(key.as_str(), &pin()).is_some());
+            });
+
+            let guard = &pin();
+            let report = l.validate(guard);
+            assert!(report.is_ok(), "{}", report);
+            assert!(report.live.is_empty());
+            assert_eq!(nested_maps(&l, guard), prefixes.len());
+        }
+    }
+
+    #[test]
+    fn test_parallel_long_keys() {
+        let l = Arc::new(MdList::<String, usize>::new());
+        (0..1_000_usize).into_par_iter().for_each(|i| {
+            l.insert(format!("a long shared prefix/{:04}", i), i);
+        });
+
+        let guard = &pin();
+        for (idx, (key, value)) in l.iter(guard).enumerate() {
+            assert_eq!(key, format!("a long shared prefix/{:04}", idx));
+            assert_eq!(*value, idx);
+        }
+        assert_eq!(l.starts_with("a long shared prefix/09", guard).count(), 100);
+
+        let report = l.validate(guard);
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.live.len(), 1_000);
+    }
+
+    #[test]
+    fn test_conditional_updates() {
+        let guard = &pin();
+        let l = MdList::<u64, u64>::new();
+
+        assert_eq!(l.insert_if_absent(1_u64, 10, guard), Ok(&10));
+        assert_eq!(l.insert_if_absent(1_u64, 11, guard), Err(&10));
+        assert_eq!(l.get(1_u64, guard), Some(&10));
+
+        assert_eq!(l.compare_and_swap(1_u64, &11, 12, guard), Err(Some(&10)));
+        assert_eq!(l.compare_and_swap(1_u64, &10, 12, guard), Ok(&10));
+        assert_eq!(l.compare_and_swap(2_u64, &10, 12, guard), Err(None));
+        assert_eq!(l.get(1_u64, guard), Some(&12));
+
+        assert_eq!(l.update(1_u64, |v| v * 2, guard), Some(&24));
+        assert_eq!(l.update(2_u64, |v| v * 2, guard), None);
+        assert_eq!(l.get(2_u64, guard), None);
+
+        // A removed key is absent again
+        l.remove(1_u64, guard);
+        assert_eq!(l.update(1_u64, |v| v * 2, guard), None);
+        assert_eq!(l.insert_if_absent(1_u64, 13, guard), Ok(&13));
+
+        let l = MdList::<String, u64, 4>::new();
+        assert_eq!(l.insert_if_absent("a longer key", 1, guard), Ok(&1));
+        assert_eq!(l.insert_if_absent("a longer key", 2, guard), Err(&1));
+        assert_eq!(l.compare_and_swap("a longer key", &1, 2, guard), Ok(&1));
+        assert_eq!(l.update("a longer key", |v| v + 1, guard), Some(&3));
+        assert_eq!(l.update("a longer", |v| v + 1, guard), None);
+    }
+
+    #[test]
+    fn test_parallel_counters() {
+        let l = Arc::new(MdList::<u64, u64>::new());
+        for key in 0..8_u64 {
+            l.insert(key, 0);
+        }
+
+        (0..8_000_u64).into_par_iter().for_each(|i| {
+            let guard = &pin();
+            if i % 2 == 0 {
+                l.update(i % 8, |v| v + 1, guard);
+            } else {
+                // Optimistic increment
+                let mut current = *l.get(i % 8, guard).unwrap();
+                while let Err(present) = l.compare_and_swap(i % 8, &current, current + 1, guard) {
+                    current = *present.unwrap();
+                }
+            }
+        });
+
+        let guard = &pin();
+        for key in 0..8_u64 {
+            assert_eq!(l.get(key, guard), Some(&1_000));
+        }
+
+        // Exactly one insert wins per key
+        let l = Arc::new(MdList::<u64, u64>::new());
+        let wins: usize = (0..8_000_u64)
+            .into_par_iter()
+            .map(|i| l.insert_if_absent(i % 100, i, &pin()).is_ok() as usize)
+            .sum();
+        assert_eq!(wins, 100);
+        assert!(l.validate(&pin()).is_ok(), "{}", l.validate(&pin()));
+    }
+
+    #[test]
+    fn test_entry() {
+        let guard = &pin();
+        let l = MdList::<String, u64, 4>::new();
+
+        assert_eq!(*l.entry("a", guard).or_insert(1), 1);
+        assert_eq!(*l.entry("a", guard).or_insert(2), 1);
+        assert_eq!(*l.entry("a longer key", guard).or_default(), 0);
+        assert_eq!(*l.entry("a longer key", guard).or_insert_with(|| 3), 0);
+
+        match l.entry("a", guard) {
+            Entry::Occupied(entry) => {
+                assert_eq!(*entry.get(), 1);
+                assert_eq!(entry.replace(4), Ok(&1));
+            }
+            Entry::Vacant(_) => panic!("`a` is occupied"),
+        }
+        assert_eq!(l.get("a", guard), Some(&4));
+
+        // The entry is out of date once the value is replaced
+        let Entry::Occupied(stale) = l.entry("a", guard) else {
+            panic!("`a` is occupied")
+        };
+        l.insert("a", 5);
+        assert_eq!(stale.replace(6), Err(Some(&5)));
+
+        let Entry::Vacant(vacant) = l.entry("b", guard) else {
+            panic!("`b` is vacant")
+        };
+        l.insert("b", 7);
+        assert_eq!(vacant.insert(8), Err(&7));
+
+        let Entry::Occupied(entry) = l.entry("b", guard) else {
+            panic!("`b` is occupied")
+        };
+        assert_eq!(entry.remove(), Ok(&7));
+        assert!(matches!(l.entry("b", guard), Entry::Vacant(_)));
+
+        // A stale entry leaves the value that replaced it in place
+        let Entry::Occupied(stale) = l.entry("a", guard) else {
+            panic!("`a` is occupied")
+        };
+        l.insert("a", 9);
+        assert_eq!(stale.remove(), Err(Some(&9)));
+        assert_eq!(l.get("a", guard), Some(&9));
+    }
+
+    #[test]
+    fn test_parallel_entry_remove() {
+        // Every value is removed through an entry at most once, and only by
+        // a thread that found that very value
+        let l = MdList::<u64, u64>::new();
+        l.insert(0_u64, 0);
+        let removed = (1..=8_000_u64)
+            .into_par_iter()
+            .map(|i| {
+                let guard = &pin();
+                if i % 2 == 0 {
+                    l.insert(0_u64, i);
+                    return vec![];
+                }
+                match l.entry(0_u64, guard) {
+                    Entry::Occupied(entry) => {
+                        let found = *entry.get();
+                        match entry.remove() {
+                            Ok(&value) => {
+                                assert_eq!(value, found);
+                                vec![value]
+                            }
+                            Err(present) => {
+                                assert_ne!(present, Some(&found));
+                                vec![]
+                            }
+                        }
+                    }
+                    Entry::Vacant(_) => vec![],
+                }
+            })
+            .flatten()
+            .collect::<Vec<_>>();
+
+        let mut unique = removed.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), removed.len());
+        assert!(l.validate(&pin()).is_ok(), "{}", l.validate(&pin()));
+    }
+
+    #[test]
+    fn test_parallel_interning() {
+        let l = Arc::new(MdList::<String, Box<u64>>::new());
+        let interned = (0..8_000_u64)
+            .into_par_iter()
+            .map(|i| {
+                let guard = &pin();
+                let value = l
+                    .entry(format!("interned string #{}", i % 100), guard)
+                    .or_insert_with(|| Box::new(i % 100));
+                (i % 100, &**value as *const u64 as usize)
+            })
+            .collect::<Vec<_>>();
+
+        // Every thread got the one value that won the race for its key
+        let guard = &pin();
+        for (key, ptr) in interned {
+            let value = l.get(format!("interned string #{}", key), guard).unwrap();
+            assert_eq!(**value, key);
+            assert_eq!(&**value as *const u64 as usize, ptr);
+        }
+    }
+
+    #[test]
+    fn test_query_box() {
+        let guard = &pin();
+        let l = MdList::<String, u32, 2>::new();
+        for (idx, key) in ["aa", "ab", "ba", "bb", "bc", "ca", "cb", "bbc"]
+            .iter()
+            .enumerate()
+        {
+            l.insert(*key, idx as u32);
+        }
+
+        let keys = |lo: &str, hi: &str| {
+            l.query_box(lo.to_coords(), hi.to_coords(), guard)
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(keys("ab", "bb"), ["ab", "bb", "bbc"]);
+        assert_eq!(
+            keys("aa", "cb"),
+            ["aa", "ab", "ba", "bb", "ca", "cb", "bbc"]
+        );
+        assert_eq!(keys("ba", "ca"), ["ba", "ca"]);
+        assert_eq!(keys("cc", "cc"), [] as [&str; 0]);
+    }
+
+    #[test]
+    fn test_nearest() {
+        let guard = &pin();
+        let l = MdList::<[u8; 8], &'static str, 8>::new();
+        l.insert(*b"abcdefgh", "abcdefgh");
+        l.insert(*b"abcdefgx", "abcdefgx");
+        l.insert(*b"abcdexyz", "abcdexyz");
+        l.insert(*b"zzzzzzzz", "zzzzzzzz");
+
+        let nearest = l.nearest(*b"abcdefgg", 2, Metric::Hamming, guard);
+        assert_eq!(
+            nearest,
+            [
+                (1, *b"abcdefgh", &"abcdefgh"),
+                (1, *b"abcdefgx", &"abcdefgx")
+            ]
+        );
+
+        let nearest = l.nearest(*b"abcdefgi", 3, Metric::Manhattan, guard);
+        assert_eq!(
+            nearest
+                .iter()
+                .map(|(d, _, v)| (*d, **v))
+                .collect::<Vec<_>>(),
+            [(1, "abcdefgh"), (15, "abcdefgx"), (53, "abcdexyz")]
+        );
+    }
+
+    #[test]
+    fn test_composite_keys() {
+        let guard = &pin();
+        let l = MdList::<(u32, String, u64), u64>::new();
+        for tenant in [1_u32, 2, 10] {
+            for entity in ["order", "orders", "user"] {
+                for ts in [0_u64, 5, 1 << 40] {
+                    l.insert((tenant, entity, ts), ts);
+                }
+            }
+        }
+
+        let keys = |prefix: CoordsBuilder| {
+            l.starts_with(&prefix, guard)
+                .map(|((tenant, entity, ts), _)| format!("{}/{}/{}", tenant, entity, ts))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            keys(CoordsBuilder::new().field(2_u32).field("order")),
+            ["2/order/0", "2/order/5", "2/order/1099511627776"]
+        );
+        assert_eq!(keys(CoordsBuilder::new().field(10_u32)).len(), 9);
+        assert_eq!(keys(CoordsBuilder::new().field(3_u32)).len(), 0);
+        assert_eq!(
+            keys(CoordsBuilder::new().field(1_u32).field("user").field(5_u64)),
+            ["1/user/5"]
+        );
+
+        let range = l
+            .range(
+                (2, "orders".to_string(), 1)..(2, "user".to_string(), 1),
+                guard,
+            )
+            .map(|(key, _)| key)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            range,
+            [
+                (2, "orders".to_string(), 5),
+                (2, "orders".to_string(), 1 << 40),
+                (2, "user".to_string(), 0)
+            ]
+        );
+        assert_eq!(l.get((10_u32, "user", 5_u64), guard), Some(&5));
+        assert_eq!(l.iter(guard).count(), 27);
+    }
+
+    #[test]
+    fn test_cursor() {
+        let guard = &pin();
+        let l = MdList::<String, u64, 4>::new();
+        let mut cursor = l.cursor(guard);
+        assert_eq!(cursor.next(), None);
+        assert_eq!(cursor.seek_to_last(), None);
+
+        // Keys longer than DIM sort in between the others
+        let keys = ["a", "ab", "abcdef", "abcdefgh", "b", "bcdefghij", "c"];
+        for (i, key) in keys.iter().enumerate() {
+            l.insert(*key, i as u64);
+        }
+
+        let mut cursor = l.cursor(guard);
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.prev(), None);
+        let forward = cursor.by_ref().map(|(k, _)| k).collect::<Vec<_>>();
+        assert_eq!(forward, keys);
+        assert_eq!(cursor.current(), None);
+
+        let mut backward = vec![];
+        while let Some((key, _)) = cursor.prev() {
+            backward.push(key);
+        }
+        backward.reverse();
+        assert_eq!(backward, keys);
+
+        assert_eq!(cursor.seek("abc"), Some(("abcdef".to_string(), &2)));
+        assert_eq!(cursor.prev(), Some(("ab".to_string(), &1)));
+        assert_eq!(cursor.next(), Some(("abcdef".to_string(), &2)));
+        assert_eq!(
+            cursor.seek_after("abcdef"),
+            Some(("abcdefgh".to_string(), &3))
+        );
+        assert_eq!(cursor.seek("b"), Some(("b".to_string(), &4)));
+        assert_eq!(cursor.seek("d"), None);
+        assert_eq!(cursor.prev(), Some(("c".to_string(), &6)));
+        assert_eq!(cursor.seek_to_first(), Some(("a".to_string(), &0)));
+        assert_eq!(cursor.seek_to_last(), Some(("c".to_string(), &6)));
+
+        // The cursor finds its way back after its entry is removed
+        cursor.seek("b");
+        l.remove("b", guard);
+        l.insert("ba", 7);
+        assert_eq!(cursor.current(), Some(("b".to_string(), &4)));
+        assert_eq!(cursor.prev(), Some(("abcdefgh".to_string(), &3)));
+        cursor.seek_after("abcdefgh");
+        assert_eq!(cursor.current(), Some(("ba".to_string(), &7)));
+    }
+
+    #[test]
+    fn test_parallel_paging() {
+        let l = MdList::<u64, u64>::new();
+        for i in (0..10_000).step_by(2) {
+            l.insert(i, i);
+        }
+
+        // Pages stay in order and see every key that was there all along,
+        // while odd keys are inserted in between
+        rayon::join(
+            || {
+                (1..10_000)
+                    .step_by(2)
+                    .par_bridge()
+                    .for_each(|i| l.insert(i, i));
+            },
+            || {
+                let guard = &pin();
+                let mut token = None;
+                let mut seen = vec![];
+                loop {
+                    let mut cursor = l.cursor(guard);
+                    let page = match token {
+                        Some(token) => cursor.seek_after(token).into_iter().chain(cursor.take(99)),
+                        None => cursor.seek_to_first().into_iter().chain(cursor.take(99)),
+                    }
+                    .map(|(k, _)| k)
+                    .collect::<Vec<_>>();
+                    let Some(&last) = page.last() else { break };
+                    token = Some(last);
+                    seen.extend(page);
+                }
+
+                assert!(seen.windows(2).all(|w| w[0] < w[1]));
+                let even = seen.iter().filter(|k| *k % 2 == 0).count();
+                assert_eq!(even, 5_000);
+            },
+        );
+    }
+
+    #[test]
+    fn test_par_iter() {
+        let guard = &pin();
+        let l = MdList::<u64, u64>::new();
+        assert_eq!(l.par_iter(guard).count(), 0);
+
+        // Small keys share their leading coordinates
+        (0..50_000_u64)
+            .into_par_iter()
+            .for_each(|i| l.insert(i * 3, i));
+        let mut keys = l.par_iter(guard).map(|(k, _)| k).collect::<Vec<_>>();
+        keys.sort_unstable();
+        assert_eq!(keys, (0..50_000).map(|i| i * 3).collect::<Vec<_>>());
+        assert_eq!(
+            l.par_iter(guard).map(|(_, v)| *v).sum::<u64>(),
+            (0..50_000).sum::<u64>()
+        );
+    }
+
+    #[test]
+    fn test_par_starts_with() {
+        let guard = &pin();
+        let l = MdList::<String, usize, 4>::new();
+        let keys = (0..3_000)
+            .map(|i| format!("{}/{:x}", ["ab", "abcdef", "b"][i % 3], i))
+            .collect::<Vec<_>>();
+        keys.par_iter()
+            .enumerate()
+            .for_each(|(i, k)| l.insert(k.as_str(), i));
+
+        for prefix in ["", "a", "ab", "abcd", "abcde", "abcdef/1", "c"] {
+            let mut found = l
+                .par_starts_with(prefix, guard)
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>();
+            found.sort();
+            let expected = l
+                .starts_with(prefix, guard)
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>();
+            assert_eq!(found, expected, "prefix {:?}", prefix);
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! Runs random sequences of operations on an [`MdList`] and on a `BTreeMap`,
+//! and checks that they agree after every step.
+//!
+//! Keys are drawn from small ranges and alphabets, so that operations keep
+//! hitting the same keys and the same prefixes. A failing sequence is shrunk
+//! by proptest to a minimal one, which it stores under `proptest-regressions`
+//! to replay first on later runs.
+
+use core::fmt::Debug;
+use std::collections::BTreeMap;
+
+use proptest::prelude::*;
+use rayon::prelude::*;
+
+use super::{FromCoords, MdList, ToCoords};
+use crate::ebr::pin;
+
+#[derive(Debug, Clone)]
+enum Op<K> {
+    Insert(K, u32),
+    Get(K),
+    Remove(K),
+    /// The coordinates of the key, cut off after the given number
+    StartsWith(K, usize),
+}
+
+fn ops<K: Debug + Clone>(
+    keys: impl Strategy<Value = K> + Clone,
+) -> impl Strategy<Value = Vec<Op<K>>> {
+    let op = prop_oneof![
+        3 => (keys.clone(), any::<u32>()).prop_map(|(key, value)| Op::Insert(key, value)),
+        2 => keys.clone().prop_map(Op::Get),
+        2 => keys.clone().prop_map(Op::Remove),
+        1 => (keys, 0..40_usize).prop_map(|(key, len)| Op::StartsWith(key, len)),
+    ];
+    prop::collection::vec(op, 1..200)
+}
+
+/// The coordinates of `key`, one chunk after the other.
+fn flat<K: ToCoords<DIM>, const DIM: usize>(key: K) -> Vec<u8> {
+    key.to_chunks().concat()
+}
+
+fn check<K, const DIM: usize>(ops: Vec<Op<K>>) -> Result<(), TestCaseError>
+where
+    K: ToCoords<DIM> + FromCoords<DIM> + Ord + Clone + Debug,
+{
+    let guard = &pin();
+    let map = MdList::<K, u32, DIM>::new();
+    let mut model = BTreeMap::new();
+
+    for op in ops {
+        match op {
+            Op::Insert(key, value) => {
+                map.insert(key.clone(), value);
+                model.insert(key, value);
+            }
+            Op::Get(key) => {
+                prop_assert_eq!(map.get(key.clone(), guard), model.get(&key));
+            }
+            Op::Remove(key) => {
+                let removed = model.remove(&key);
+                prop_assert_eq!(map.remove(key, guard), removed.as_ref());
+            }
+            Op::StartsWith(key, len) => {
+                let mut prefix = flat(key);
+                prefix.truncate(len);
+                let found = map
+                    .starts_with(&prefix, guard)
+                    .map(|(k, &v)| (k, v))
+                    .collect::<Vec<_>>();
+                let expected = model
+                    .iter()
+                    .filter(|(k, _)| flat((*k).clone()).starts_with(&prefix))
+                    .map(|(k, &v)| (k.clone(), v))
+                    .collect::<Vec<_>>();
+                prop_assert_eq!(found, expected, "prefix {:?}", prefix);
+            }
+        }
+    }
+
+    let found = map.iter(guard).map(|(k, &v)| (k, v)).collect::<Vec<_>>();
+    prop_assert_eq!(found, model.into_iter().collect::<Vec<_>>());
+    let report = map.validate(guard);
+    prop_assert!(report.is_ok(), "{}", report);
+    Ok(())
+}
+
+/// Inserts `keys` from all threads at once, then removes every other one
+/// the same way, and compares the result with the model.
+fn check_parallel<K, const DIM: usize>(keys: Vec<K>) -> Result<(), TestCaseError>
+where
+    K: ToCoords<DIM> + FromCoords<DIM> + Ord + Clone + Debug + Send + Sync,
+{
+    let map = MdList::<K, usize, DIM>::new();
+    keys.par_iter().enumerate().for_each(|(i, key)| {
+        map.insert(key.clone(), i % 2);
+    });
+    // Of duplicate keys, any insert may have won
+    keys.par_iter().for_each(|key| {
+        if map.get(key.clone(), &pin()) == Some(&0) {
+            map.remove(key.clone(), &pin());
+        }
+    });
+
+    let guard = &pin();
+    let model = keys
+        .iter()
+        .enumerate()
+        .filter(|(i, key)| i % 2 == 1 && !keys.iter().step_by(2).any(|other| other == *key))
+        .map(|(_, key)| key.clone())
+        .collect::<std::collections::BTreeSet<_>>();
+    for key in &model {
+        prop_assert_eq!(map.get(key.clone(), guard), Some(&1));
+    }
+
+    let mut report = map.validate(guard);
+    report.expect_keys(model.iter().cloned());
+    prop_assert!(report.is_ok(), "{}", report);
+    prop_assert!(map.iter(guard).all(|(_, &v)| v == 1));
+    Ok(())
+}
+
+fn small_u64() -> impl Strategy<Value = u64> + Clone {
+    prop_oneof![0..64_u64, any::<u64>()]
+}
+
+fn short_string() -> impl Strategy<Value = String> + Clone {
+    "[abc]{0,10}"
+}
+
+proptest! {
+    #[test]
+    fn model_u64_bytes(ops in ops(small_u64())) {
+        check::<u64, 8>(ops)?;
+    }
+
+    #[test]
+    fn model_u64_nibbles(ops in ops(small_u64())) {
+        check::<u64, 16>(ops)?;
+    }
+
+    #[test]
+    fn model_u64_bits(ops in ops(small_u64())) {
+        check::<u64, 64>(ops)?;
+    }
+
+    #[test]
+    fn model_i64(ops in ops(prop_oneof![-32..32_i64, any::<i64>()])) {
+        check::<i64, 8>(ops)?;
+    }
+
+    #[test]
+    fn model_u16_nibbles(ops in ops(any::<u16>())) {
+        check::<u16, 4>(ops)?;
+    }
+
+    #[test]
+    fn model_string_chunked(ops in ops(short_string())) {
+        check::<String, 4>(ops)?;
+    }
+
+    #[test]
+    fn model_string(ops in ops(short_string())) {
+        check::<String, 16>(ops)?;
+    }
+}
+
+proptest! {
+    // Every case already runs on all threads
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn model_parallel_u64(keys in prop::collection::vec(small_u64(), 0..500)) {
+        check_parallel::<u64, 16>(keys)?;
+    }
+
+    #[test]
+    fn model_parallel_string(keys in prop::collection::vec(short_string(), 0..500)) {
+        check_parallel::<String, 4>(keys)?;
+    }
+}
@@ -0,0 +1,210 @@
+//! Parallel iteration over an [`MdList`], see [`MdList::par_iter`].
+
+use core::ops::Bound;
+
+use rayon::iter::plumbing::{bridge_unindexed, Folder, UnindexedConsumer, UnindexedProducer};
+use rayon::iter::{self, Either, ParallelIterator};
+
+use super::{FromCoords, MdList, Partition};
+use crate::ebr::{pin, Guard};
+
+impl<const DIM: usize, K, T> MdList<K, T, DIM> {
+    /// Returns the first chunk of the first key in `partition`, or `None`
+    /// if it has no keys.
+    fn first_chunk<'g>(
+        &'g self,
+        partition: &Partition<DIM>,
+        guard: &'g Guard,
+    ) -> Option<[u8; DIM]> {
+        let (lo, hi) = partition.bounds();
+        self.chunks_range(lo, hi, guard)
+            .next()
+            .map(|(chunks, _)| chunks[0])
+    }
+
+    /// Splits `partition`, whose first key starts with `first`, for rayon.
+    /// Halves without keys are dropped on the way, so the partitions close
+    /// in on where the keys are. Each half comes with the first chunk of its
+    /// first key.
+    ///
+    /// Whether the left half has keys follows from `first`, so each halving
+    /// only looks up the first key of the right half.
+    fn split_partition<'g>(
+        &'g self,
+        mut partition: Partition<DIM>,
+        first: [u8; DIM],
+        guard: &'g Guard,
+    ) -> Split<DIM> {
+        while let Some((left, right)) = partition.halve() {
+            if first > left.hi() {
+                partition = right;
+                continue;
+            }
+            match self.first_chunk(&right, guard) {
+                Some(next) => return ((left, first), Some((right, next))),
+                None => partition = left,
+            }
+        }
+        ((partition, first), None)
+    }
+
+    /// Scans the keys in `partition` in parallel, in parts that rayon splits
+    /// off for idle threads. `'g` keeps `guard` pinned for as long as the
+    /// entries are around, see [`ParChunks`].
+    fn par_chunks<'g>(
+        &'g self,
+        partition: Partition<DIM>,
+        prefix: Vec<[u8; DIM]>,
+        guard: &'g Guard,
+    ) -> impl 'g + ParallelIterator<Item = (K, &'g T)>
+    where
+        K: Send + Sync + FromCoords<DIM>,
+        T: Send + Sync,
+    {
+        ParChunks {
+            list: self,
+            partition,
+            first: self.first_chunk(&partition, guard),
+            prefix,
+        }
+    }
+
+    /// Returns the entries in parallel, in no particular order.
+    ///
+    /// The keyspace is split by leading coordinates into partitions that
+    /// hold keys, which are scanned independently. The scans are weakly
+    /// consistent, like [`MdList::iter`].
+    pub fn par_iter<'g>(&'g self, guard: &'g Guard) -> impl 'g + ParallelIterator<Item = (K, &'g T)>
+    where
+        K: Send + Sync + FromCoords<DIM>,
+        T: Send + Sync,
+    {
+        self.par_chunks(Partition::full(), vec![], guard)
+    }
+
+    /// Returns the entries whose coordinates start with `prefix` in
+    /// parallel, see [`MdList::par_iter`]. The prefix may be longer than
+    /// `DIM`.
+    pub fn par_starts_with<'g, Q: ?Sized + AsRef<[u8]>>(
+        &'g self,
+        prefix: &Q,
+        guard: &'g Guard,
+    ) -> impl 'g + ParallelIterator<Item = (K, &'g T)>
+    where
+        K: Send + Sync + FromCoords<DIM>,
+        T: Send + Sync,
+    {
+        // Only the last chunk of the prefix is split, the ones before it
+        // lead to a single nested map
+        let mut prefix = prefix.as_ref();
+        let mut chunks = vec![];
+        while prefix.len() > DIM {
+            let (first, rest) = prefix.split_at(DIM);
+            chunks.push(<[u8; DIM]>::try_from(first).unwrap());
+            prefix = rest;
+        }
+
+        match self.nested(&chunks, guard) {
+            Some(level) => {
+                Either::Left(level.par_chunks(Partition::starting_with(prefix), chunks, guard))
+            }
+            None => Either::Right(iter::empty()),
+        }
+    }
+}
+
+/// The halves of a split partition, with the first chunks of their first
+/// keys
+type Split<const DIM: usize> = (
+    (Partition<DIM>, [u8; DIM]),
+    Option<(Partition<DIM>, [u8; DIM])>,
+);
+
+/// The keys of a [`Partition`] with their values, scanned in parts that are
+/// split off for idle rayon threads.
+///
+/// Each thread pins a guard of its own to split and scan its parts, as the
+/// guard the iterator was created with is pinned on another thread. The
+/// values outlive the thread's guard, for `'g`, which is fine while that
+/// other guard is pinned. A scan only returns nodes that it reached through
+/// the list under the guard of its thread, and it starts after the other
+/// guard was pinned. So a node it returns, even one inserted after that, is
+/// retired after the other guard was pinned, if at all, and ebr does not
+/// reclaim it before that guard is unpinned.
+struct ParChunks<'g, K, T, const DIM: usize> {
+    list: &'g MdList<K, T, DIM>,
+    partition: Partition<DIM>,
+    /// The first chunk of the first key in the partition, as of the last
+    /// lookup, or `None` if it had no keys. The scan starts there, so keys
+    /// inserted since are only found if they come after it.
+    first: Option<[u8; DIM]>,
+    prefix: Vec<[u8; DIM]>,
+}
+
+impl<'g, K, T, const DIM: usize> ParallelIterator for ParChunks<'g, K, T, DIM>
+where
+    K: Send + Sync + FromCoords<DIM>,
+    T: Send + Sync,
+{
+    type Item = (K, &'g T);
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        bridge_unindexed(self, consumer)
+    }
+}
+
+impl<'g, K, T, const DIM: usize> UnindexedProducer for ParChunks<'g, K, T, DIM>
+where
+    K: Send + Sync + FromCoords<DIM>,
+    T: Send + Sync,
+{
+    type Item = (K, &'g T);
+
+    fn split(self) -> (Self, Option<Self>) {
+        let Some(first) = self.first else {
+            return (self, None);
+        };
+        let ((left, first), right) = self.list.split_partition(self.partition, first, &pin());
+        let right = right.map(|(partition, first)| Self {
+            partition,
+            first: Some(first),
+            prefix: self.prefix.clone(),
+            ..self
+        });
+        (
+            Self {
+                partition: left,
+                first: Some(first),
+                ..self
+            },
+            right,
+        )
+    }
+
+    fn fold_with<F>(self, folder: F) -> F
+    where
+        F: Folder<Self::Item>,
+    {
+        let Some(first) = self.first else {
+            return folder;
+        };
+        // There were no keys before `first` when it was looked up
+        let (_, hi) = self.partition.bounds();
+        let lo = Bound::Included(vec![first]);
+        let guard = &pin();
+        let entries = self
+            .list
+            .chunks_range(lo, hi, guard)
+            .map(|(chunks, value)| {
+                let chunks = [self.prefix.as_slice(), &chunks].concat();
+                // SAFETY: the node was reachable under our guard, after the
+                // guard of `par_chunks` was pinned, which keeps it from being
+                // reclaimed for `'g`, see above
+                (K::from_chunks(&chunks), unsafe { &*(value as *const T) })
+            });
+        folder.consume_iter(entries)
+    }
+}
@@ -0,0 +1,363 @@
+//! Splitting the keyspace of an [`MdList`] into ranges of coordinates, to
+//! spread one map over several.
+
+use core::ops::Bound;
+
+use super::{MdList, ToCoords};
+use crate::ebr::Guard;
+
+/// A bound on chunked keys
+type ChunksBound<const DIM: usize> = Bound<Vec<[u8; DIM]>>;
+
+/// The keys whose first chunk of coordinates lies between `lo` and `hi`,
+/// both included.
+///
+/// Keys longer than `DIM` belong to the partition of their first chunk, so
+/// a partition always holds whole nested maps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Partition<const DIM: usize> {
+    lo: [u8; DIM],
+    hi: [u8; DIM],
+}
+
+impl<const DIM: usize> Partition<DIM> {
+    /// The partition between `lo` and `hi`, which is empty if `lo > hi`.
+    pub fn new(lo: [u8; DIM], hi: [u8; DIM]) -> Self {
+        Self { lo, hi }
+    }
+
+    /// The whole keyspace
+    pub fn full() -> Self {
+        Self::new([0; DIM], [u8::MAX; DIM])
+    }
+
+    /// The keys that start with `prefix`, which fits in a chunk.
+    pub fn starting_with(prefix: &[u8]) -> Self {
+        let mut partition = Self::full();
+        partition.lo[..prefix.len()].copy_from_slice(prefix);
+        partition.hi[..prefix.len()].copy_from_slice(prefix);
+        partition
+    }
+
+    pub fn lo(&self) -> [u8; DIM] {
+        self.lo
+    }
+
+    pub fn hi(&self) -> [u8; DIM] {
+        self.hi
+    }
+
+    /// Returns `true` if `key` belongs to the partition.
+    pub fn contains<Q: ToCoords<DIM>>(&self, key: Q) -> bool {
+        let first = key.to_chunks()[0];
+        self.lo <= first && first <= self.hi
+    }
+
+    pub(super) fn bounds(&self) -> (ChunksBound<DIM>, ChunksBound<DIM>) {
+        // Longer keys with `hi` as their first chunk lie past `hi` itself
+        let hi = match successor(self.hi) {
+            Some(next) => Bound::Excluded(vec![next]),
+            None => Bound::Unbounded,
+        };
+        (Bound::Included(vec![self.lo]), hi)
+    }
+
+    /// Splits the partition in half by coordinates, or `None` if it holds a
+    /// single chunk.
+    pub(super) fn halve(&self) -> Option<(Self, Self)> {
+        if self.lo >= self.hi {
+            return None;
+        }
+
+        let mid = midpoint(self.lo, self.hi);
+        let right = Self::new(successor(mid).unwrap(), self.hi);
+        Some((Self::new(self.lo, mid), right))
+    }
+}
+
+/// The coordinates right after `coords`, or `None` past the last ones.
+fn successor<const DIM: usize>(mut coords: [u8; DIM]) -> Option<[u8; DIM]> {
+    for c in coords.iter_mut().rev() {
+        let (next, overflow) = c.overflowing_add(1);
+        *c = next;
+        if !overflow {
+            return Some(coords);
+        }
+    }
+    None
+}
+
+/// The coordinates right before `coords`, or `None` before the first ones.
+fn predecessor<const DIM: usize>(mut coords: [u8; DIM]) -> Option<[u8; DIM]> {
+    for c in coords.iter_mut().rev() {
+        let (prev, overflow) = c.overflowing_sub(1);
+        *c = prev;
+        if !overflow {
+            return Some(coords);
+        }
+    }
+    None
+}
+
+/// Rounds down halfway between `lo` and `hi`, read as big-endian numbers.
+fn midpoint<const DIM: usize>(lo: [u8; DIM], hi: [u8; DIM]) -> [u8; DIM] {
+    let mut sum = [0_u16; DIM];
+    let mut carry = 0;
+    for d in (0..DIM).rev() {
+        let s = lo[d] as u16 + hi[d] as u16 + carry;
+        sum[d] = s & 0xff;
+        carry = s >> 8;
+    }
+
+    let mut mid = [0; DIM];
+    let mut rem = carry;
+    for d in 0..DIM {
+        let v = (rem << 8) | sum[d];
+        mid[d] = (v >> 1) as u8;
+        rem = v & 1;
+    }
+    mid
+}
+
+impl<const DIM: usize, K, T> MdList<K, T, DIM> {
+    /// Splits the keyspace into `n` partitions that hold about as many keys
+    /// each, in key order.
+    ///
+    /// The boundaries are the first chunks of the keys at every `1 / n`-th
+    /// of the map, which takes two scans. Keys that share their first chunk
+    /// stay together, and if there are fewer first chunks than partitions,
+    /// the widest partitions are halved until there are `n`.
+    pub fn partitions(&self, n: usize, guard: &Guard) -> Vec<Partition<DIM>> {
+        assert!(n > 0, "the keyspace has at least one partition");
+        let entries = || self.chunks_range(Bound::Unbounded, Bound::Unbounded, guard);
+        let len = entries().count();
+
+        // The first chunk of the entries that start a partition
+        let mut starts = vec![[0; DIM]];
+        for (idx, (chunks, _)) in entries().enumerate() {
+            if idx * n >= starts.len() * len && starts.last() != Some(&chunks[0]) {
+                starts.push(chunks[0]);
+            }
+        }
+
+        let mut partitions = starts
+            .windows(2)
+            .map(|pair| Partition::new(pair[0], predecessor(pair[1]).unwrap()))
+            .collect::<Vec<_>>();
+        partitions.push(Partition::new(*starts.last().unwrap(), [u8::MAX; DIM]));
+
+        while partitions.len() < n {
+            let widest = (0..partitions.len())
+                .max_by_key(|&idx| {
+                    let Partition { lo, hi } = partitions[idx];
+                    (0..DIM)
+                        .find(|&d| lo[d] != hi[d])
+                        .map(|d| (DIM - d, hi[d] - lo[d]))
+                })
+                .unwrap();
+            let Some((left, right)) = partitions[widest].halve() else {
+                panic!("the keyspace has fewer than {} coordinates", n);
+            };
+            partitions.splice(widest..=widest, [left, right]);
+        }
+        partitions
+    }
+
+    /// Moves the entries in `partition` to a new map.
+    ///
+    /// Entries are moved one at a time, so readers may find an entry in
+    /// either map or, in between, in neither. An entry inserted into the
+    /// partition while it is moved may stay behind.
+    ///
+    /// Values are stored in their nodes, which readers may still hold after
+    /// the entry is removed, so each value is cloned into the new map rather
+    /// than moved. That costs a clone and an allocation per entry, the
+    /// originals are dropped when their nodes are reclaimed. Values that are
+    /// expensive to clone can be stored behind an `Arc`.
+    pub fn split_off(&self, partition: &Partition<DIM>, guard: &Guard) -> Self
+    where
+        T: Clone,
+    {
+        let other = Self::new();
+        let (lo, hi) = partition.bounds();
+        for (chunks, _) in self.chunks_range(lo, hi, guard) {
+            // What we remove is what moves, even if it was replaced since
+            if let Some(value) = self.remove_chunks(&chunks, guard) {
+                other.insert_chunks(&chunks, value.clone());
+            }
+        }
+        other
+    }
+
+    /// Moves all entries of `other` into this map, replacing the values of
+    /// keys that are in both.
+    ///
+    /// Unlike [`MdList::split_off`], this moves the values: nobody else can
+    /// reach the nodes of `other` anymore, so they are taken apart as they
+    /// are, without waiting for pinned threads.
+    pub fn merge(&self, mut other: Self) {
+        other.drain_into(&mut vec![], &mut |chunks, value| {
+            self.insert_chunks(chunks, value)
+        });
+    }
+
+    /// Moves the entries out of the map, leaving it empty, and hands them to
+    /// `f` with the chunks of their keys after `prefix`.
+    fn drain_into(&mut self, prefix: &mut Vec<[u8; DIM]>, f: &mut impl FnMut(&[[u8; DIM]], T)) {
+        self.list.drain(|coords, node| {
+            prefix.push(coords);
+            f(prefix, node.value);
+            prefix.pop();
+        });
+
+        let mut nested = vec![];
+        self.overflow
+            .drain(|chunk, node| nested.push((chunk, node.value)));
+        for (chunk, mut map) in nested {
+            prefix.push(chunk);
+            map.drain_into(prefix, f);
+            prefix.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ebr::pin;
+
+    #[test]
+    fn test_halve() {
+        assert_eq!(midpoint([0, 0], [0, 255]), [0, 127]);
+        assert_eq!(midpoint([0, 255], [1, 0]), [0, 255]);
+        assert_eq!(midpoint([0, 0], [255, 255]), [127, 255]);
+        assert_eq!(midpoint([254, 255], [255, 255]), [255, 127]);
+        assert_eq!(successor([0, 255]), Some([1, 0]));
+        assert_eq!(successor([255, 255]), None);
+        assert_eq!(predecessor([1, 0]), Some([0, 255]));
+        assert_eq!(predecessor([0, 0]), None);
+
+        // Halving covers the partition without overlap
+        let (left, right) = Partition::<2>::starting_with(&[7]).halve().unwrap();
+        assert_eq!(left, Partition::new([7, 0], [7, 127]));
+        assert_eq!(right, Partition::new([7, 128], [7, 255]));
+        assert_eq!(Partition::new([3, 3], [3, 3]).halve(), None);
+    }
+
+    fn assert_covering<const DIM: usize>(partitions: &[Partition<DIM>]) {
+        assert_eq!(partitions.first().unwrap().lo(), [0; DIM]);
+        assert_eq!(partitions.last().unwrap().hi(), [u8::MAX; DIM]);
+        for pair in partitions.windows(2) {
+            assert!(pair[0].lo() <= pair[0].hi());
+            assert_eq!(successor(pair[0].hi()), Some(pair[1].lo()));
+        }
+    }
+
+    #[test]
+    fn test_partitions() {
+        let guard = &pin();
+        let l = MdList::<u64, u64>::new();
+        assert_eq!(l.partitions(3, guard).len(), 3);
+        assert_covering(&l.partitions(3, guard));
+
+        for i in 0..10_000 {
+            l.insert(i * i, i);
+        }
+        for n in [1, 2, 7, 64] {
+            let partitions = l.partitions(n, guard);
+            assert_eq!(partitions.len(), n);
+            assert_covering(&partitions);
+
+            // Skewed keys still end up evenly spread
+            for partition in &partitions {
+                let (lo, hi) = partition.bounds();
+                let len = l.chunks_range(lo, hi, guard).count();
+                assert!(
+                    len.abs_diff(10_000 / n) <= 1,
+                    "{} keys in {:?}",
+                    len,
+                    partition
+                );
+            }
+        }
+
+        // More partitions than keys
+        let l = MdList::<u64, u64>::new();
+        l.insert(5, 5);
+        let partitions = l.partitions(4, guard);
+        assert_eq!(partitions.len(), 4);
+        assert_covering(&partitions);
+        assert_eq!(partitions.iter().filter(|p| p.contains(5_u64)).count(), 1);
+    }
+
+    #[test]
+    fn test_split_off_merge() {
+        let guard = &pin();
+        let l = MdList::<String, usize, 4>::new();
+        let keys = (0..500).map(|i| format!("key {}", i)).collect::<Vec<_>>();
+        for (i, key) in keys.iter().enumerate() {
+            l.insert(key.as_str(), i);
+        }
+
+        let partitions = l.partitions(3, guard);
+        let parts = partitions
+            .iter()
+            .map(|partition| l.split_off(partition, guard))
+            .collect::<Vec<_>>();
+        assert_eq!(l.iter(guard).count(), 0);
+
+        for (partition, part) in partitions.iter().zip(&parts) {
+            assert!(part
+                .iter(guard)
+                .all(|(key, _)| partition.contains(key.as_str())));
+        }
+
+        for part in parts {
+            l.merge(part);
+        }
+        let merged = l.iter(guard).map(|(key, &i)| (key, i)).collect::<Vec<_>>();
+        let mut expected = keys
+            .into_iter()
+            .enumerate()
+            .map(|(i, k)| (k, i))
+            .collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn test_merge_moves() {
+        use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        // Not `Clone`, and counts how often it is dropped
+        struct Value(usize);
+
+        impl Drop for Value {
+            fn drop(&mut self) {
+                DROPPED.fetch_add(1, Relaxed);
+            }
+        }
+
+        let guard = &pin();
+        let l = MdList::<String, Value, 4>::new();
+        let other = MdList::<String, Value, 4>::new();
+        // Short and long keys, so that nested maps move as well
+        for i in 0..100 {
+            other.insert(format!("{}", i).as_str(), Value(i));
+            other.insert(format!("key {}", i).as_str(), Value(i));
+        }
+        other.remove("key 7", guard);
+        let removed = DROPPED.load(Relaxed);
+
+        l.merge(other);
+        assert_eq!(DROPPED.load(Relaxed), removed);
+        assert_eq!(l.iter(guard).count(), 199);
+        for i in 0..100 {
+            assert_eq!(l.get(format!("{}", i).as_str(), guard).unwrap().0, i);
+        }
+        assert!(l.get("key 7", guard).is_none());
+        assert_eq!(l.get("key 99", guard).unwrap().0, 99);
+    }
+}
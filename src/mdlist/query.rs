@@ -0,0 +1,346 @@
+//! Multi-dimensional queries on the coordinates of a [`List`].
+
+use core::cmp::{Ordering, Reverse};
+use core::marker::PhantomData;
+use core::sync::atomic::Ordering::{Acquire, Relaxed};
+use std::collections::BinaryHeap;
+
+use super::{IsElement, List, Node};
+use crate::ebr::{Guard, Shared};
+
+/// Iterates over the nodes of a [`List`] that lie in a box, see
+/// [`List::query_box`].
+///
+/// A node reached through dimension `d` agrees with everything below it on
+/// the coordinates before `d`, and everything below it has a coordinate at
+/// `d` that is at least as large. So once a coordinate of a node falls
+/// outside the box, the children that share it are pruned, and if it lies
+/// past the upper bound so are the children that do not.
+///
+/// Nodes are returned in lexicographic order. Like [`super::Iter`], the
+/// iterator is weakly consistent.
+pub struct BoxIter<'g, const DIM: usize, T, C: IsElement<DIM, T>> {
+    guard: &'g Guard,
+    lo: [u8; DIM],
+    hi: [u8; DIM],
+    // Nodes to visit, along with the dimension we reached them through
+    stack: Vec<(Shared<'g, Node<DIM>>, usize)>,
+    _marker: PhantomData<(&'g T, C)>,
+}
+
+impl<'g, const DIM: usize, T: 'g, C: IsElement<DIM, T>> Iterator for BoxIter<'g, DIM, T, C> {
+    type Item = &'g T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        unsafe {
+            while let Some((node, dim)) = self.stack.pop() {
+                let coords = &node.deref().coords;
+
+                // Coordinates before `dim` were checked on the way down
+                let out = (dim..DIM)
+                    .find(|&d| coords[d] < self.lo[d] || coords[d] > self.hi[d])
+                    .unwrap_or(DIM);
+
+                // Children in dimensions up to `out` may still lie in the
+                // box, but not if `coords[out]` is already too large. Pushed
+                // in ascending order so the last dimension is visited first.
+                let end = if out < DIM && coords[out] > self.hi[out] {
+                    out
+                } else {
+                    DIM.min(out + 1)
+                };
+                for d in dim..end {
+                    let child = node.deref().children.load(d, Acquire, self.guard);
+                    if !child.is_null() {
+                        self.stack.push((child, d));
+                    }
+                }
+
+                if out == DIM && node.tag() & List::<DIM, T, C>::DEL == 0 {
+                    return Some(C::element_of(node.deref()));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Distance between two coordinate vectors, as the sum of a distance in
+/// each dimension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    /// Sum of absolute differences
+    Manhattan,
+    /// Sum of squared differences, the square of the Euclidean distance,
+    /// which orders points the same way
+    Euclidean,
+    /// Number of differing coordinates
+    Hamming,
+}
+
+impl Metric {
+    #[inline]
+    fn dist(self, a: u8, b: u8) -> u64 {
+        let diff = a.abs_diff(b) as u64;
+        match self {
+            Metric::Manhattan => diff,
+            Metric::Euclidean => diff * diff,
+            Metric::Hamming => (diff != 0) as u64,
+        }
+    }
+
+    /// Lower bound on the distance from `query` to the node at `coords` and
+    /// everything below it, given that we reached it through `dim`.
+    ///
+    /// Everything below agrees with the node on the coordinates before `dim`
+    /// and has a coordinate at `dim` that is at least as large, so it gets no
+    /// closer there once the node is past `query`.
+    #[inline]
+    fn bound<const DIM: usize>(self, query: &[u8; DIM], coords: &[u8; DIM], dim: usize) -> u64 {
+        let shared: u64 = (0..dim).map(|d| self.dist(query[d], coords[d])).sum();
+        if coords[dim] > query[dim] {
+            shared + self.dist(query[dim], coords[dim])
+        } else {
+            shared
+        }
+    }
+
+    #[inline]
+    fn distance<const DIM: usize>(self, a: &[u8; DIM], b: &[u8; DIM]) -> u64 {
+        (0..DIM).map(|d| self.dist(a[d], b[d])).sum()
+    }
+}
+
+/// A subtree to search, ordered by its lower bound
+struct Candidate<'g, const DIM: usize> {
+    bound: u64,
+    node: Shared<'g, Node<DIM>>,
+    dim: usize,
+}
+
+impl<const DIM: usize> PartialEq for Candidate<'_, DIM> {
+    fn eq(&self, other: &Self) -> bool {
+        self.bound == other.bound
+    }
+}
+
+impl<const DIM: usize> Eq for Candidate<'_, DIM> {}
+
+impl<const DIM: usize> PartialOrd for Candidate<'_, DIM> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<const DIM: usize> Ord for Candidate<'_, DIM> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.bound.cmp(&other.bound)
+    }
+}
+
+impl<const DIM: usize, T, C: IsElement<DIM, T>> List<DIM, T, C> {
+    /// Iterates over the nodes with `lo[d] <= coords[d] <= hi[d]` in every
+    /// dimension `d`, see [`BoxIter`].
+    pub fn query_box<'g>(
+        &'g self,
+        lo: [u8; DIM],
+        hi: [u8; DIM],
+        guard: &'g Guard,
+    ) -> BoxIter<'g, DIM, T, C> {
+        let head = self.head.load(Relaxed, guard);
+        let root = unsafe { head.deref().children.load(0, Acquire, guard) };
+        BoxIter {
+            guard,
+            lo,
+            hi,
+            stack: if root.is_null() {
+                vec![]
+            } else {
+                vec![(root, 0)]
+            },
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the `k` nodes closest to `query` under `metric`, along with
+    /// their distance, closest first. Ties are broken by lexicographic order.
+    ///
+    /// This is a best-first search over subtrees, ordered by a lower bound on
+    /// the distance of everything in them, see `Metric::bound`. Once `k`
+    /// nodes are found, the search stops at the first subtree that cannot
+    /// hold anything closer.
+    pub fn nearest<'g>(
+        &'g self,
+        query: [u8; DIM],
+        k: usize,
+        metric: Metric,
+        guard: &'g Guard,
+    ) -> Vec<(u64, &'g T)> {
+        if k == 0 {
+            return vec![];
+        }
+
+        let head = self.head.load(Relaxed, guard);
+        let root = unsafe { head.deref().children.load(0, Acquire, guard) };
+
+        let mut candidates = BinaryHeap::new();
+        if !root.is_null() {
+            let bound = metric.bound(&query, unsafe { &root.deref().coords }, 0);
+            candidates.push(Reverse(Candidate {
+                bound,
+                node: root,
+                dim: 0,
+            }));
+        }
+
+        // The closest nodes so far, the farthest on top
+        let mut found = BinaryHeap::<(u64, [u8; DIM], *const Node<DIM>)>::new();
+        while let Some(Reverse(Candidate { bound, node, dim })) = candidates.pop() {
+            if found.len() == k && found.peek().is_some_and(|&(worst, ..)| bound > worst) {
+                break;
+            }
+
+            let n = unsafe { node.deref() };
+            if node.tag() & Self::DEL == 0 {
+                let entry = (metric.distance(&query, &n.coords), n.coords, node.as_raw());
+                if found.len() < k {
+                    found.push(entry);
+                } else if found.peek().is_some_and(|worst| entry < *worst) {
+                    found.pop();
+                    found.push(entry);
+                }
+            }
+
+            for d in dim..DIM {
+                let child = n.children.load(d, Acquire, guard);
+                if !child.is_null() {
+                    let bound = metric.bound(&query, unsafe { &child.deref().coords }, d);
+                    candidates.push(Reverse(Candidate {
+                        bound,
+                        node: child,
+                        dim: d,
+                    }));
+                }
+            }
+        }
+
+        found
+            .into_sorted_vec()
+            .into_iter()
+            .map(|(dist, _, node)| (dist, unsafe { C::element_of(&*node) }))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use super::*;
+    use crate::ebr::pin;
+    use crate::mdlist::NodeWithValue;
+
+    #[test]
+    fn test_query_box() {
+        let guard = &pin();
+        let l: List<3, NodeWithValue<3, u32>> = List::new();
+        let points = (0..4_000_u32)
+            .map(|i| {
+                let h = i.wrapping_mul(2_654_435_761);
+                [h as u8 % 32, (h >> 8) as u8 % 32, (h >> 16) as u8 % 32]
+            })
+            .collect::<Vec<_>>();
+        points.par_iter().for_each(|p| unsafe {
+            let guard = &pin();
+            let elem = NodeWithValue::alloc(NodeWithValue::new(*p, 0), guard);
+            l.insert(elem, guard);
+        });
+
+        let mut sorted = points.clone();
+        sorted.sort();
+        sorted.dedup();
+        for (i, p) in sorted.iter().enumerate() {
+            if i % 4 == 0 {
+                unsafe { l.remove(*p, guard) };
+            }
+        }
+
+        let boxes = [
+            ([0, 0, 0], [31, 31, 31]),
+            ([4, 0, 0], [9, 31, 31]),
+            ([0, 10, 0], [31, 12, 31]),
+            ([0, 0, 30], [31, 31, 31]),
+            ([5, 5, 5], [10, 20, 15]),
+            ([7, 7, 7], [7, 7, 7]),
+            ([10, 0, 0], [5, 31, 31]),
+        ];
+        for (lo, hi) in boxes {
+            let expected = sorted
+                .iter()
+                .enumerate()
+                .filter(|&(i, p)| i % 4 != 0 && (0..3).all(|d| lo[d] <= p[d] && p[d] <= hi[d]))
+                .map(|(_, p)| *p)
+                .collect::<Vec<_>>();
+            let found = l
+                .query_box(lo, hi, guard)
+                .map(|n| n.node.coords)
+                .collect::<Vec<_>>();
+            assert_eq!(found, expected, "box {:?}..={:?}", lo, hi);
+        }
+
+        let empty: List<3, NodeWithValue<3, u32>> = List::new();
+        assert_eq!(empty.query_box([0; 3], [u8::MAX; 3], guard).count(), 0);
+    }
+
+    #[test]
+    fn test_nearest() {
+        let guard = &pin();
+        let l: List<4, NodeWithValue<4, u32>> = List::new();
+        let points = (0..2_000_u32)
+            .map(|i| i.wrapping_mul(2_654_435_761).to_be_bytes().map(|b| b % 16))
+            .collect::<Vec<_>>();
+        points.par_iter().for_each(|p| unsafe {
+            let guard = &pin();
+            let elem = NodeWithValue::alloc(NodeWithValue::new(*p, 0), guard);
+            l.insert(elem, guard);
+        });
+
+        let mut sorted = points.clone();
+        sorted.sort();
+        sorted.dedup();
+        for p in sorted.iter().step_by(5) {
+            unsafe { l.remove(*p, guard) };
+        }
+        let live = sorted
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| i % 5 != 0)
+            .map(|(_, p)| *p)
+            .collect::<Vec<_>>();
+
+        let queries = [[0, 0, 0, 0], [7, 8, 7, 8], [15, 15, 15, 15], [3, 12, 0, 9]];
+        for metric in [Metric::Manhattan, Metric::Euclidean, Metric::Hamming] {
+            for query in queries {
+                for k in [0, 1, 5, 40] {
+                    let mut expected = live
+                        .iter()
+                        .map(|p| (metric.distance(&query, p), *p))
+                        .collect::<Vec<_>>();
+                    expected.sort();
+                    expected.truncate(k);
+
+                    let found = l
+                        .nearest(query, k, metric, guard)
+                        .into_iter()
+                        .map(|(dist, n)| (dist, n.node.coords))
+                        .collect::<Vec<_>>();
+                    assert_eq!(found, expected, "{:?} {:?} k = {}", metric, query, k);
+                }
+            }
+        }
+
+        let empty: List<4, NodeWithValue<4, u32>> = List::new();
+        assert!(empty.nearest([0; 4], 3, Metric::Hamming, guard).is_empty());
+    }
+}
@@ -0,0 +1,244 @@
+//! A concurrent priority queue on top of [`List`], which is what the mdlist
+//! was designed for in the first place. Pushes and pops are both lock-free:
+//! a pop marks the first node with `DEL` and unlinks it, and any thread that
+//! runs into the node while it is being unlinked helps to finish the unlink
+//! rather than wait for it, see [`List::unlink`].
+
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
+use core::ops::Bound;
+use core::sync::atomic::{AtomicU64, Ordering::Relaxed};
+
+use super::{Alloc, FromCoords, IsElement, NodeWithValue, ToCoords, Values};
+use crate::ebr::{pin, unprotected};
+
+/// Dimensions taken by the priority, which is followed by a sequence number
+/// of the same width.
+const PRIORITY_DIM: usize = 16;
+const DIM: usize = 2 * PRIORITY_DIM;
+
+/// A concurrent min-priority queue.
+///
+/// Each element is keyed by its priority followed by a sequence number, so
+/// equal priorities are allowed and popped in the order they were pushed.
+/// The smallest element is the first node in lexicographic order, so
+/// [`MdPriorityQueue::pop_min`] follows the leftmost path from the head and
+/// logically deletes the first node that is not deleted yet, see
+/// [`List::first`].
+///
+/// Finding the minimum is weakly consistent with concurrent pushes: an
+/// element pushed while a pop is under way may be passed over by it.
+pub struct MdPriorityQueue<P, T> {
+    // Values are moved out by the thread that deletes their node, see
+    // `pop_min`, so the list must not drop them
+    list: Values<DIM, ManuallyDrop<T>>,
+    seq: AtomicU64,
+    _ph: PhantomData<P>,
+}
+
+impl<P, T> Default for MdPriorityQueue<P, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P, T> MdPriorityQueue<P, T> {
+    pub fn new() -> Self {
+        Self {
+            list: Values::new(),
+            seq: AtomicU64::new(0),
+            _ph: PhantomData,
+        }
+    }
+
+    /// Pushes `value` with `priority`.
+    pub fn push(&self, priority: P, value: T)
+    where
+        P: ToCoords<PRIORITY_DIM>,
+    {
+        let seq = self.seq.fetch_add(1, Relaxed);
+        let mut coords = [0; DIM];
+        coords[..PRIORITY_DIM].copy_from_slice(&priority.to_coords());
+        coords[PRIORITY_DIM..].copy_from_slice(&ToCoords::<PRIORITY_DIM>::to_coords(seq));
+
+        let guard = &pin();
+        unsafe {
+            let elem = Alloc::alloc(NodeWithValue::new(coords, ManuallyDrop::new(value)), guard);
+            self.list.insert(elem, guard)
+        }
+    }
+
+    /// Removes the element with the smallest priority, or the one pushed
+    /// first among equal priorities.
+    pub fn pop_min(&self) -> Option<(P, T)>
+    where
+        P: FromCoords<PRIORITY_DIM>,
+    {
+        let guard = &pin();
+        loop {
+            let min = unsafe { self.list.first(guard)? };
+            let coords = min.node.coords;
+
+            // Another thread may delete it first, then we look again. Either
+            // way some pop succeeded.
+            if let Some(popped) = unsafe { self.list.remove(coords, guard) } {
+                // Only the thread that deleted the node gets here, so the
+                // value is moved out exactly once
+                let value = ManuallyDrop::into_inner(unsafe { core::ptr::read(&popped.value) });
+                let priority = P::from_coords(coords[..PRIORITY_DIM].try_into().unwrap());
+                return Some((priority, value));
+            }
+        }
+    }
+
+    /// Returns `true` if there are no elements, at the time of the call.
+    pub fn is_empty(&self) -> bool {
+        let guard = &pin();
+        unsafe { self.list.first(guard).is_none() }
+    }
+}
+
+impl<P, T> Drop for MdPriorityQueue<P, T> {
+    fn drop(&mut self) {
+        // Popped values were moved out already, so only drop the rest
+        unsafe {
+            let guard = unprotected();
+            for node in self.list.range(Bound::Unbounded, Bound::Unbounded, guard) {
+                drop(ManuallyDrop::into_inner(core::ptr::read(&node.value)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use std::cmp::Reverse;
+    use std::collections::BinaryHeap;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn priorities(n: u64) -> impl Iterator<Item = u64> {
+        (0..n).map(|i| i.wrapping_mul(2_654_435_761) % 1_000)
+    }
+
+    #[test]
+    fn test_sequential() {
+        let q = MdPriorityQueue::<u64, u64>::new();
+        let mut heap = BinaryHeap::new();
+        assert!(q.is_empty());
+        assert_eq!(q.pop_min(), None);
+
+        // Pop every third step, the sequence number breaks ties in both
+        for (seq, priority) in priorities(3_000).enumerate() {
+            q.push(priority, seq as u64);
+            heap.push(Reverse((priority, seq as u64)));
+            if seq % 3 == 2 {
+                assert_eq!(q.pop_min(), heap.pop().map(|Reverse(e)| e));
+            }
+        }
+
+        while let Some(Reverse(expected)) = heap.pop() {
+            assert_eq!(q.pop_min(), Some(expected));
+        }
+        assert!(q.is_empty());
+        assert_eq!(q.pop_min(), None);
+    }
+
+    #[test]
+    fn test_parallel() {
+        let q = Arc::new(MdPriorityQueue::<u64, u64>::new());
+        let heap = priorities(10_000).collect::<BinaryHeap<_>>();
+
+        priorities(10_000)
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .for_each(|priority| q.push(priority, priority));
+
+        // Without concurrent pushes, every thread pops in ascending order
+        let popped = std::thread::scope(|s| {
+            let handles = (0..4)
+                .map(|_| {
+                    s.spawn(|| {
+                        let mut popped = vec![];
+                        while let Some((priority, value)) = q.pop_min() {
+                            assert_eq!(priority, value);
+                            popped.push(priority);
+                        }
+                        popped
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        for popped in &popped {
+            assert!(popped.windows(2).all(|w| w[0] <= w[1]));
+        }
+
+        let mut popped = popped.concat();
+        popped.sort_unstable();
+        assert_eq!(popped, heap.into_sorted_vec());
+    }
+
+    #[test]
+    fn test_parallel_push_pop() {
+        let q = Arc::new(MdPriorityQueue::<u64, u64>::new());
+
+        // Every element is popped exactly once, by any of the threads
+        let mut popped = priorities(10_000)
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .filter_map(|priority| {
+                q.push(priority, priority);
+                q.pop_min().map(|(p, _)| p)
+            })
+            .collect::<Vec<_>>();
+        while let Some((priority, _)) = q.pop_min() {
+            popped.push(priority);
+        }
+
+        // Pops unlink the nodes they delete, and the placeholders with them
+        let report = q.list.validate(&pin());
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.routing, 0);
+
+        popped.sort_unstable();
+        assert_eq!(
+            popped,
+            priorities(10_000)
+                .collect::<BinaryHeap<_>>()
+                .into_sorted_vec()
+        );
+    }
+
+    #[test]
+    fn test_drop() {
+        struct Counted(Arc<AtomicUsize>);
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Relaxed);
+            }
+        }
+
+        let drops = Arc::new(AtomicUsize::new(0));
+        let q = MdPriorityQueue::<u64, Counted>::new();
+        for priority in 0..100_u64 {
+            q.push(priority, Counted(drops.clone()));
+        }
+        for _ in 0..40 {
+            drop(q.pop_min());
+        }
+        assert_eq!(drops.load(Relaxed), 40);
+
+        drop(q);
+        assert_eq!(drops.load(Relaxed), 100);
+    }
+}
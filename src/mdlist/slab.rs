@@ -0,0 +1,433 @@
+//! A slab allocator for list nodes, used instead of the global allocator when
+//! the `slab` feature is enabled.
+//!
+//! All nodes of a list have the same size, so they are carved out of larger
+//! chunks, and a freed node is kept for the next node of its size rather
+//! than given back. Each thread caches free blocks per layout, so allocating
+//! and freeing a node takes no synchronization. Blocks only move between a
+//! thread and the depot of their layout a batch at a time, when its cache
+//! runs empty or has grown too large, and once more when the thread exits.
+//! A depot is a Treiber stack of batches, whose popped batches are freed
+//! through ebr, so taking a batch is lock-free as well.
+//!
+//! Only the lists of maps and queues allocate from the slab, through
+//! [`Slab`]. Their nodes are freed by the functions that ebr defers until no
+//! pinned thread can reach them, see [`IsElement::finalize`], so they end up
+//! in the cache of whichever thread collects them. Chunks are never returned
+//! to the system.
+
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
+use core::ptr::{self, NonNull};
+use core::sync::atomic::AtomicPtr;
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use std::alloc::{handle_alloc_error, Layout};
+use std::cell::RefCell;
+
+use super::{Element, IsElement, Node, NodeWithValue};
+use crate::ebr::{pin, Atomic, Guard, Owned};
+
+/// Blocks carved out of each chunk
+const CHUNK_BLOCKS: usize = 256;
+
+/// Blocks moved between a thread and the depot at a time
+const BATCH: usize = 64;
+
+/// Free blocks of the same layout, on the stack of a depot. Nobody else
+/// refers to the blocks, so they may move between threads.
+struct Batch {
+    /// Moved out by whoever pops the batch, before it is freed
+    blocks: ManuallyDrop<Vec<NonNull<u8>>>,
+    next: Atomic<Batch>,
+}
+
+unsafe impl Send for Batch {}
+unsafe impl Sync for Batch {}
+
+/// The batches given up by threads for one layout. Depots are never freed,
+/// and are found through the list starting at [`DEPOTS`].
+struct Depot {
+    layout: Layout,
+    /// The top of the stack of batches
+    batches: Atomic<Batch>,
+    next: *const Depot,
+}
+
+unsafe impl Sync for Depot {}
+
+/// The most recently added depot, one per layout
+static DEPOTS: AtomicPtr<Depot> = AtomicPtr::new(ptr::null_mut());
+
+impl Depot {
+    /// Returns the depot for `layout`, adding it if there is none yet.
+    fn of(layout: Layout) -> &'static Depot {
+        let mut new = None;
+        loop {
+            let head = DEPOTS.load(Acquire);
+            let mut depot = head as *const Depot;
+            while let Some(d) = unsafe { depot.as_ref() } {
+                if d.layout == layout {
+                    return d;
+                }
+                depot = d.next;
+            }
+
+            let mut d = new.take().unwrap_or_else(|| {
+                Box::new(Depot {
+                    layout,
+                    batches: Atomic::null(),
+                    next: ptr::null(),
+                })
+            });
+            d.next = head;
+            let d = Box::into_raw(d);
+            match DEPOTS.compare_exchange(head, d, Release, Relaxed) {
+                Ok(_) => return unsafe { &*d },
+                // Another depot was added, which may be for the same layout
+                Err(_) => new = Some(unsafe { Box::from_raw(d) }),
+            }
+        }
+    }
+
+    fn put(&self, blocks: Vec<NonNull<u8>>) {
+        let guard = &pin();
+        let mut batch = Owned::new(Batch {
+            blocks: ManuallyDrop::new(blocks),
+            next: Atomic::null(),
+        });
+        loop {
+            let top = self.batches.load(Relaxed, guard);
+            batch.next.store(top, Relaxed);
+            match self.batches.compare_and_set(top, batch, Release, guard) {
+                Ok(_) => return,
+                Err(e) => batch = e.new,
+            }
+        }
+    }
+
+    fn take(&self) -> Option<Vec<NonNull<u8>>> {
+        // The top may be popped and freed by another thread while we read
+        // its successor, the guard keeps it around until we are done
+        let guard = &pin();
+        loop {
+            let top = self.batches.load(Acquire, guard);
+            let next = unsafe { top.as_ref()? }.next.load(Relaxed, guard);
+            if self
+                .batches
+                .compare_and_set(top, next, Relaxed, guard)
+                .is_ok()
+            {
+                unsafe {
+                    let blocks = ptr::read(&top.deref().blocks);
+                    guard.defer_destroy(top);
+                    return Some(ManuallyDrop::into_inner(blocks));
+                }
+            }
+        }
+    }
+}
+
+/// Carves a new chunk into blocks of `layout`.
+fn carve(layout: Layout) -> Vec<NonNull<u8>> {
+    // The size of a layout is a multiple of its alignment, so every block
+    // in the chunk is aligned
+    let size = layout.size();
+    let chunk = Layout::from_size_align(size * CHUNK_BLOCKS, layout.align()).unwrap();
+    let base = unsafe { std::alloc::alloc(chunk) };
+    if base.is_null() {
+        handle_alloc_error(chunk);
+    }
+
+    // Handed out in address order
+    (0..CHUNK_BLOCKS)
+        .rev()
+        .map(|i| unsafe { NonNull::new_unchecked(base.add(i * size)) })
+        .collect()
+}
+
+/// The free blocks of one layout that a thread holds on to.
+///
+/// Pinning may run deferred frees, which go into the cache of this thread,
+/// so the depot is only used once the cache is no longer borrowed.
+struct Class {
+    depot: &'static Depot,
+    free: Vec<NonNull<u8>>,
+}
+
+impl Class {
+    fn new(layout: Layout) -> Self {
+        Self {
+            depot: Depot::of(layout),
+            free: vec![],
+        }
+    }
+
+    /// Takes a cached block, or returns the depot to refill the cache from.
+    fn pop(&mut self) -> Result<NonNull<u8>, &'static Depot> {
+        self.free.pop().ok_or(self.depot)
+    }
+
+    fn refill(&mut self, blocks: Vec<NonNull<u8>>) -> NonNull<u8> {
+        self.free.extend(blocks);
+        self.free.pop().unwrap()
+    }
+
+    /// Caches a freed block. Returns a batch for the depot if the cache has
+    /// grown too large.
+    fn push(&mut self, block: NonNull<u8>) -> Option<(&'static Depot, Vec<NonNull<u8>>)> {
+        self.free.push(block);
+        // The blocks freed last are the most likely to be cached, so the
+        // ones at the bottom go
+        if self.free.len() >= 2 * BATCH {
+            return Some((self.depot, self.free.drain(..BATCH).collect()));
+        }
+        None
+    }
+}
+
+impl Drop for Class {
+    fn drop(&mut self) {
+        if !self.free.is_empty() {
+            self.depot.put(core::mem::take(&mut self.free));
+        }
+    }
+}
+
+thread_local! {
+    static CACHE: RefCell<Vec<Class>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Calls `f` with the cache of this thread for `layout`.
+fn with_class<R>(layout: Layout, f: impl FnOnce(&mut Class) -> R) -> R {
+    let mut f = Some(f);
+    let cached = CACHE.try_with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let i = match cache.iter().position(|class| class.depot.layout == layout) {
+            Some(i) => i,
+            None => {
+                cache.push(Class::new(layout));
+                cache.len() - 1
+            }
+        };
+        f.take().unwrap()(&mut cache[i])
+    });
+
+    // An exiting thread may still collect garbage after its cache is gone,
+    // then the blocks go through a class of their own
+    match cached {
+        Ok(r) => r,
+        Err(_) => f.unwrap()(&mut Class::new(layout)),
+    }
+}
+
+/// Moves `value` into a block of the slab.
+pub(crate) fn alloc<T>(value: T) -> NonNull<T> {
+    let layout = Layout::new::<T>();
+    assert!(layout.size() > 0, "zero sized values take no block");
+
+    let block = match with_class(layout, Class::pop) {
+        Ok(block) => block,
+        Err(depot) => {
+            let blocks = depot.take().unwrap_or_else(|| carve(layout));
+            with_class(layout, |class| class.refill(blocks))
+        }
+    }
+    .cast::<T>();
+    unsafe { block.as_ptr().write(value) };
+    block
+}
+
+/// Drops the value at `ptr` and frees its block.
+///
+/// # Safety
+///
+/// `ptr` was returned by [`alloc`], and is neither freed nor used afterwards.
+pub(crate) unsafe fn free<T>(ptr: NonNull<T>) {
+    // The value may own nodes of its own, which are freed here as well, so
+    // it is dropped before the cache is borrowed
+    ptr::drop_in_place(ptr.as_ptr());
+    release(ptr);
+}
+
+/// Moves the value at `ptr` out and frees its block.
+///
+/// # Safety
+///
+/// Same as for [`free`].
+pub(crate) unsafe fn take<T>(ptr: NonNull<T>) -> T {
+    let value = ptr::read(ptr.as_ptr());
+    release(ptr);
+    value
+}
+
+/// Gives the block at `ptr` back to the cache, without dropping it.
+fn release<T>(ptr: NonNull<T>) {
+    if let Some((depot, batch)) = with_class(Layout::new::<T>(), |class| class.push(ptr.cast())) {
+        depot.put(batch);
+    }
+}
+
+/// Allocates [`NodeWithValue`]s from the slab. Lists of it only take nodes
+/// that it allocated, since it frees them into the slab.
+pub struct Slab<V>(PhantomData<V>);
+
+impl<const DIM: usize, V> IsElement<DIM, NodeWithValue<DIM, V>> for Slab<V> {
+    fn entry_of(entry: &NodeWithValue<DIM, V>) -> &Node<DIM> {
+        NodeWithValue::entry_of(entry)
+    }
+
+    unsafe fn element_of(entry: &Node<DIM>) -> &NodeWithValue<DIM, V> {
+        NodeWithValue::element_of(entry)
+    }
+
+    fn alloc<'g>(
+        element: NodeWithValue<DIM, V>,
+        _: &'g Guard,
+    ) -> Element<'g, NodeWithValue<DIM, V>, Self> {
+        Element {
+            shared: (alloc(element).as_ptr() as *const NodeWithValue<DIM, V>).into(),
+            _alloc: PhantomData,
+        }
+    }
+
+    unsafe fn dealloc(element: Element<'_, NodeWithValue<DIM, V>, Self>) {
+        free(NonNull::from(element.deref()));
+    }
+
+    unsafe fn finalize(entry: &Node<DIM>, guard: &Guard) {
+        let element = NonNull::from(<Self as IsElement<DIM, _>>::element_of(entry));
+        guard.defer_unchecked(move || free(element));
+    }
+
+    unsafe fn take(entry: &Node<DIM>) -> NodeWithValue<DIM, V> {
+        take(NonNull::from(<Self as IsElement<DIM, _>>::element_of(
+            entry,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn test_reuse() {
+        let a = alloc([1_u64; 4]);
+        let b = alloc([2_u64; 4]);
+        assert_eq!(unsafe { *a.as_ptr() }, [1; 4]);
+        assert_eq!(unsafe { *b.as_ptr() }, [2; 4]);
+
+        // Freed blocks are handed out again, last in first out
+        unsafe { free(a) };
+        let c = alloc([3_u64; 4]);
+        assert_eq!(c.cast::<u8>(), a.cast::<u8>());
+        unsafe {
+            free(b);
+            free(c);
+        }
+    }
+
+    #[test]
+    fn test_drop() {
+        let value = Arc::new(());
+        let block = alloc(value.clone());
+        assert_eq!(Arc::strong_count(&value), 2);
+        unsafe { free(block) };
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn test_depot() {
+        // A layout of its own, so the depot is not shared with other tests.
+        // The blocks are never dereferenced, any address will do.
+        let depot = Depot::of(Layout::from_size_align(3 * 1024, 8).unwrap());
+        let taken = std::thread::scope(|s| {
+            let handles = (0..4_usize)
+                .map(|t| {
+                    s.spawn(move || {
+                        let mut taken = vec![];
+                        for i in 0..1000 {
+                            let block = NonNull::new((8 * (t * 1000 + i + 1)) as *mut u8).unwrap();
+                            depot.put(vec![block]);
+                            if i % 2 == 0 {
+                                let batch = depot.take().unwrap();
+                                taken.extend(batch.iter().map(|b| b.as_ptr() as usize));
+                            }
+                        }
+                        taken
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        // Every batch is taken at most once, and none gets lost
+        let mut blocks = taken.iter().copied().collect::<HashSet<_>>();
+        assert_eq!(blocks.len(), taken.len());
+        while let Some(batch) = depot.take() {
+            for block in batch {
+                assert!(blocks.insert(block.as_ptr() as usize));
+            }
+        }
+        assert_eq!(blocks.len(), 4000);
+        assert!(core::ptr::eq(depot, Depot::of(depot.layout)));
+    }
+
+    #[test]
+    fn test_threads() {
+        // Blocks allocated on one thread and freed on another come back
+        // through the depot, and are never handed out twice
+        let blocks = std::thread::scope(|s| {
+            let handles = (0..4)
+                .map(|t| {
+                    s.spawn(move || {
+                        (0..3 * CHUNK_BLOCKS)
+                            .map(|i| alloc((t as usize, i, 0_u8)).as_ptr() as usize)
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(blocks.iter().collect::<HashSet<_>>().len(), blocks.len());
+
+        // Joining waits for the caches of the threads to be dropped, unlike
+        // the end of the scope
+        std::thread::scope(|s| {
+            let handles = blocks
+                .chunks(blocks.len() / 2)
+                .map(|chunk| {
+                    s.spawn(move || {
+                        for &block in chunk {
+                            unsafe {
+                                free(NonNull::new(block as *mut (usize, usize, u8)).unwrap())
+                            };
+                        }
+                    })
+                })
+                .collect::<Vec<_>>();
+            for handle in handles {
+                handle.join().unwrap();
+            }
+        });
+
+        let again = (0..blocks.len())
+            .map(|i| alloc((0_usize, i, 0_u8)).as_ptr() as usize)
+            .collect::<Vec<_>>();
+        assert_eq!(again.iter().collect::<HashSet<_>>().len(), again.len());
+        let freed = blocks.iter().collect::<HashSet<_>>();
+        assert!(again.iter().all(|block| freed.contains(block)));
+        for block in again {
+            unsafe { free(NonNull::new(block as *mut (usize, usize, u8)).unwrap()) };
+        }
+    }
+}
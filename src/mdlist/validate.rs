@@ -0,0 +1,314 @@
+//! Checking the structure of a [`List`] or [`MdList`] against the invariants
+//! that lookups and iteration rely on, see [`List::validate`].
+//!
+//! A node only stores the dimension it was first linked through, see
+//! [`children`](super::children). The one it hangs off its parent in now is
+//! implied by its child pointers instead: a node reached through dimension
+//! `d` has all of its children before `d` marked with `ADP`, since those
+//! belong to whichever node it was inserted or adopted under, and none of
+//! its children from `d` on.
+//!
+//! The checks only hold while the list is quiescent. With concurrent updates
+//! in flight, pending adoptions and frozen pointers are expected.
+
+use core::fmt;
+use core::sync::atomic::Ordering::Acquire;
+
+use super::{IsElement, List, MdList, Node, ToCoords};
+use crate::ebr::{Guard, Shared};
+
+/// A broken invariant, at the node with the chunked key `at`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation<const DIM: usize> {
+    /// The child in dimension `dim` does not agree with the node on the
+    /// coordinates before `dim`, or is not larger in `dim`. The child is not
+    /// followed any further.
+    Order {
+        at: Vec<[u8; DIM]>,
+        dim: usize,
+        child: [u8; DIM],
+    },
+    /// The traversal returned `at` after `prev`, so the two are not in
+    /// lexicographic order, or the same node is reachable twice.
+    Unsorted { at: Vec<[u8; DIM]>, prev: [u8; DIM] },
+    /// The node was reached through `dim`, but the `ADP` marks on its child
+    /// pointers disagree at `child`: an unmarked pointer before `dim` or a
+    /// leftover mark after it. A leftover mark hides the child from every
+    /// traversal.
+    Dimension {
+        at: Vec<[u8; DIM]>,
+        dim: usize,
+        child: usize,
+    },
+    /// The child pointer in `child` is still frozen by an unlink.
+    Frozen { at: Vec<[u8; DIM]>, child: usize },
+    /// The node still has an adoption descriptor.
+    PendingAdoption { at: Vec<[u8; DIM]> },
+    /// An expected key is not reachable, see [`Report::expect_keys`].
+    Missing { at: Vec<[u8; DIM]> },
+}
+
+impl<const DIM: usize> fmt::Display for Violation<DIM> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Order { at, dim, child } => {
+                write!(
+                    f,
+                    "{:?}: child {:?} out of order in dimension {}",
+                    at, child, dim
+                )
+            }
+            Violation::Unsorted { at, prev } => {
+                write!(f, "{:?}: reached after {:?}", at, prev)
+            }
+            Violation::Dimension { at, dim, child } => write!(
+                f,
+                "{:?}: reached through dimension {}, but child {} is {}marked as adopted",
+                at,
+                dim,
+                child,
+                if child < dim { "not " } else { "" }
+            ),
+            Violation::Frozen { at, child } => write!(f, "{:?}: child {} is frozen", at, child),
+            Violation::PendingAdoption { at } => write!(f, "{:?}: adoption pending", at),
+            Violation::Missing { at } => write!(f, "{:?}: missing", at),
+        }
+    }
+}
+
+/// What [`List::validate`] or [`MdList::validate`] found.
+#[derive(Debug, Clone, Default)]
+pub struct Report<const DIM: usize> {
+    /// Chunked keys of the nodes that are not deleted, in ascending order
+    pub live: Vec<Vec<[u8; DIM]>>,
+    /// Deleted nodes that are still linked in: placeholders for removed
+    /// nodes that had children, and nodes whose unlinks are in progress
+    pub routing: usize,
+    pub violations: Vec<Violation<DIM>>,
+}
+
+impl<const DIM: usize> Report<DIM> {
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// Records every key of `keys` that is not live as missing.
+    pub fn expect_keys<Q: ToCoords<DIM>>(&mut self, keys: impl IntoIterator<Item = Q>) {
+        for key in keys {
+            let chunks = key.to_chunks();
+            if self.live.binary_search(&chunks).is_err() {
+                self.violations.push(Violation::Missing { at: chunks });
+            }
+        }
+    }
+}
+
+impl<const DIM: usize> fmt::Display for Report<DIM> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} live nodes, {} routing nodes, {} violations",
+            self.live.len(),
+            self.routing,
+            self.violations.len()
+        )?;
+        for violation in &self.violations {
+            write!(f, "\n  {}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+impl<const DIM: usize, T, C: IsElement<DIM, T>> List<DIM, T, C> {
+    /// Walks the whole list and checks its structure, see [`Report`].
+    pub fn validate(&self, guard: &Guard) -> Report<DIM> {
+        let mut report = Report::default();
+        for (coords, _) in self.validate_into(&[], &mut report, guard) {
+            report.live.push(vec![coords]);
+        }
+        report
+    }
+
+    /// Checks the list, whose keys continue `prefix`, and records the
+    /// violations in `report`. Returns the live elements in ascending order.
+    pub(super) fn validate_into<'g>(
+        &'g self,
+        prefix: &[[u8; DIM]],
+        report: &mut Report<DIM>,
+        guard: &'g Guard,
+    ) -> Vec<([u8; DIM], &'g T)> {
+        let at = |coords: &[u8; DIM]| [prefix, &[*coords]].concat();
+        let mut live = vec![];
+        let mut prev: Option<[u8; DIM]> = None;
+
+        unsafe {
+            let head = self.head.load(Acquire, guard);
+            let root = head.deref().children.load(0, Acquire, guard);
+
+            // Nodes to visit in ascending order, along with the dimension we
+            // reached them through, same as `Iter`
+            let mut stack: Vec<(Shared<'g, Node<DIM>>, usize)> = vec![];
+            if root.tag() & Self::FRZ != 0 {
+                report.violations.push(Violation::Frozen {
+                    at: prefix.to_vec(),
+                    child: 0,
+                });
+            }
+            if !root.is_null() {
+                stack.push((root, 0));
+            }
+
+            while let Some((node, dim)) = stack.pop() {
+                let n = node.deref();
+                if prev.is_some_and(|prev| prev >= n.coords) {
+                    report.violations.push(Violation::Unsorted {
+                        at: at(&n.coords),
+                        prev: prev.unwrap(),
+                    });
+                }
+                prev = Some(n.coords);
+
+                if node.tag() & Self::DEL != 0 {
+                    report.routing += 1;
+                } else {
+                    live.push((n.coords, C::element_of(n)));
+                }
+
+                if !n.adesc.load(Acquire, guard).is_null() {
+                    report
+                        .violations
+                        .push(Violation::PendingAdoption { at: at(&n.coords) });
+                }
+
+                for d in 0..DIM {
+                    let child = n.children.load(d, Acquire, guard);
+                    if (child.tag() & Self::ADP != 0) != (d < dim) {
+                        report.violations.push(Violation::Dimension {
+                            at: at(&n.coords),
+                            dim,
+                            child: d,
+                        });
+                    }
+                    if d >= dim && child.tag() & Self::FRZ != 0 {
+                        report.violations.push(Violation::Frozen {
+                            at: at(&n.coords),
+                            child: d,
+                        });
+                    }
+                }
+
+                for d in dim..DIM {
+                    let child = n.children.load(d, Acquire, guard);
+                    if child.is_null() || child.tag() & Self::ADP != 0 {
+                        continue;
+                    }
+
+                    let c = &child.deref().coords;
+                    if c[..d] != n.coords[..d] || c[d] <= n.coords[d] {
+                        report.violations.push(Violation::Order {
+                            at: at(&n.coords),
+                            dim: d,
+                            child: *c,
+                        });
+                        continue;
+                    }
+                    stack.push((child, d));
+                }
+            }
+        }
+        live
+    }
+}
+
+impl<const DIM: usize, K, T> MdList<K, T, DIM> {
+    /// Checks the structure of the map and of every nested map, see
+    /// [`List::validate`].
+    pub fn validate(&self, guard: &Guard) -> Report<DIM> {
+        let mut report = Report::default();
+        self.validate_into(&mut vec![], &mut report, guard);
+        report.live.sort();
+        report
+    }
+
+    fn validate_into(&self, prefix: &mut Vec<[u8; DIM]>, report: &mut Report<DIM>, guard: &Guard) {
+        for (coords, _) in self.list.validate_into(prefix, report, guard) {
+            report.live.push([prefix.as_slice(), &[coords]].concat());
+        }
+        for (chunk, nested) in self.overflow.validate_into(prefix, report, guard) {
+            prefix.push(chunk);
+            nested.validate_into(prefix, report, guard);
+            prefix.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::Ordering::Relaxed;
+
+    use super::*;
+    use crate::ebr::pin;
+
+    #[test]
+    fn test_validate() {
+        let guard = &pin();
+        let l = MdList::<String, usize, 4>::new();
+        let keys = ["a", "abcd", "abcdef", "b", "bcd", "bcde"];
+        for (i, key) in keys.iter().enumerate() {
+            l.insert(*key, i);
+        }
+        l.remove("bcd", guard);
+
+        let mut report = l.validate(guard);
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.live.len(), keys.len() - 1);
+
+        report.expect_keys(keys);
+        assert_eq!(
+            report.violations,
+            vec![Violation::Missing {
+                at: "bcd".to_chunks()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_validate_broken() {
+        let guard = &pin();
+        let l = List::<2, Node<2>>::new();
+        for coords in [[1, 1], [1, 2], [2, 0]] {
+            unsafe {
+                l.insert(
+                    crate::ebr::Owned::new(Node::new(coords)).into_shared(guard),
+                    guard,
+                )
+            };
+        }
+        assert!(l.validate(guard).is_ok(), "{}", l.validate(guard));
+
+        // A leftover adoption mark on the pointer from [1, 1] to [1, 2]
+        // hides [1, 2] from every traversal
+        unsafe {
+            let root = l
+                .head
+                .load(Relaxed, guard)
+                .deref()
+                .children
+                .load(0, Relaxed, guard);
+            assert_eq!(root.deref().coords, [1, 1]);
+            let slot = root.deref().children.slot(1, guard);
+            assert_eq!(slot.load(Relaxed, guard).deref().coords, [1, 2]);
+            slot.fetch_or(List::<2, Node<2>>::ADP, Relaxed, guard);
+        }
+
+        let report = l.validate(guard);
+        assert_eq!(
+            report.violations,
+            vec![Violation::Dimension {
+                at: vec![[1, 1]],
+                dim: 0,
+                child: 1
+            }]
+        );
+    }
+}